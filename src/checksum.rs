@@ -0,0 +1,555 @@
+///
+/// Running CRC-32 (IEEE 802.3, reflected polynomial `0xEDB88320`).
+///
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Crc32 {
+    state: u32,
+}
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self { state: 0xFFFF_FFFF }
+    }
+}
+
+impl Crc32 {
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        self.state = data.iter().fold(self.state, |crc, byte| {
+            CRC32_TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+        });
+    }
+
+    pub(crate) fn value(&self) -> u32 {
+        !self.state
+    }
+}
+
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+///
+/// Running SHA-256 (FIPS 180-4).
+///
+#[derive(Clone, Debug)]
+pub(crate) struct Sha256 {
+    state: [u32; 8],
+    buffer: Vec<u8>,
+    length: u64,
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Self {
+            state: [
+                0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+                0x5be0cd19,
+            ],
+            buffer: Vec::with_capacity(64),
+            length: 0,
+        }
+    }
+}
+
+impl Sha256 {
+    pub(crate) fn update(&mut self, mut data: &[u8]) {
+        self.length += data.len() as u64;
+        if !self.buffer.is_empty() {
+            let take = (64 - self.buffer.len()).min(data.len());
+            self.buffer.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.buffer.len() < 64 {
+                return;
+            }
+            let block = std::mem::take(&mut self.buffer);
+            self.compress(&block);
+            self.buffer = block;
+            self.buffer.clear();
+        }
+        let mut blocks = data.chunks_exact(64);
+        for block in &mut blocks {
+            self.compress(block);
+        }
+        self.buffer.extend_from_slice(blocks.remainder());
+    }
+
+    pub(crate) fn finish(mut self) -> [u8; 32] {
+        let bits = self.length * 8;
+        let mut padding = vec![0x80];
+        while (self.buffer.len() + padding.len()) % 64 != 56 {
+            padding.push(0);
+        }
+        padding.extend_from_slice(&bits.to_be_bytes());
+        self.update(&padding);
+
+        let mut digest = [0u8; 32];
+        for (chunk, word) in digest.chunks_exact_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+
+    fn compress(&mut self, block: &[u8]) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(SHA256_K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (s, v) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+}
+
+const SHA512_K: [u64; 80] = [
+    0x428a2f98d728ae22,
+    0x7137449123ef65cd,
+    0xb5c0fbcfec4d3b2f,
+    0xe9b5dba58189dbbc,
+    0x3956c25bf348b538,
+    0x59f111f1b605d019,
+    0x923f82a4af194f9b,
+    0xab1c5ed5da6d8118,
+    0xd807aa98a3030242,
+    0x12835b0145706fbe,
+    0x243185be4ee4b28c,
+    0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f,
+    0x80deb1fe3b1696b1,
+    0x9bdc06a725c71235,
+    0xc19bf174cf692694,
+    0xe49b69c19ef14ad2,
+    0xefbe4786384f25e3,
+    0x0fc19dc68b8cd5b5,
+    0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275,
+    0x4a7484aa6ea6e483,
+    0x5cb0a9dcbd41fbd4,
+    0x76f988da831153b5,
+    0x983e5152ee66dfab,
+    0xa831c66d2db43210,
+    0xb00327c898fb213f,
+    0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2,
+    0xd5a79147930aa725,
+    0x06ca6351e003826f,
+    0x142929670a0e6e70,
+    0x27b70a8546d22ffc,
+    0x2e1b21385c26c926,
+    0x4d2c6dfc5ac42aed,
+    0x53380d139d95b3df,
+    0x650a73548baf63de,
+    0x766a0abb3c77b2a8,
+    0x81c2c92e47edaee6,
+    0x92722c851482353b,
+    0xa2bfe8a14cf10364,
+    0xa81a664bbc423001,
+    0xc24b8b70d0f89791,
+    0xc76c51a30654be30,
+    0xd192e819d6ef5218,
+    0xd69906245565a910,
+    0xf40e35855771202a,
+    0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8,
+    0x1e376c085141ab53,
+    0x2748774cdf8eeb99,
+    0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63,
+    0x4ed8aa4ae3418acb,
+    0x5b9cca4f7763e373,
+    0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc,
+    0x78a5636f43172f60,
+    0x84c87814a1f0ab72,
+    0x8cc702081a6439ec,
+    0x90befffa23631e28,
+    0xa4506cebde82bde9,
+    0xbef9a3f7b2c67915,
+    0xc67178f2e372532b,
+    0xca273eceea26619c,
+    0xd186b8c721c0c207,
+    0xeada7dd6cde0eb1e,
+    0xf57d4f7fee6ed178,
+    0x06f067aa72176fba,
+    0x0a637dc5a2c898a6,
+    0x113f9804bef90dae,
+    0x1b710b35131c471b,
+    0x28db77f523047d84,
+    0x32caab7b40c72493,
+    0x3c9ebe0a15c9bebc,
+    0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6,
+    0x597f299cfc657e2a,
+    0x5fcb6fab3ad6faec,
+    0x6c44198c4a475817,
+];
+
+///
+/// Running SHA-512 (FIPS 180-4).
+///
+#[derive(Clone, Debug)]
+pub(crate) struct Sha512 {
+    state: [u64; 8],
+    buffer: Vec<u8>,
+    length: u128,
+}
+
+impl Default for Sha512 {
+    fn default() -> Self {
+        Self {
+            state: [
+                0x6a09e667f3bcc908,
+                0xbb67ae8584caa73b,
+                0x3c6ef372fe94f82b,
+                0xa54ff53a5f1d36f1,
+                0x510e527fade682d1,
+                0x9b05688c2b3e6c1f,
+                0x1f83d9abfb41bd6b,
+                0x5be0cd19137e2179,
+            ],
+            buffer: Vec::with_capacity(128),
+            length: 0,
+        }
+    }
+}
+
+impl Sha512 {
+    pub(crate) fn update(&mut self, mut data: &[u8]) {
+        self.length += data.len() as u128;
+        if !self.buffer.is_empty() {
+            let take = (128 - self.buffer.len()).min(data.len());
+            self.buffer.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.buffer.len() < 128 {
+                return;
+            }
+            let block = std::mem::take(&mut self.buffer);
+            self.compress(&block);
+            self.buffer = block;
+            self.buffer.clear();
+        }
+        let mut blocks = data.chunks_exact(128);
+        for block in &mut blocks {
+            self.compress(block);
+        }
+        self.buffer.extend_from_slice(blocks.remainder());
+    }
+
+    pub(crate) fn finish(mut self) -> [u8; 64] {
+        let bits = self.length * 8;
+        let mut padding = vec![0x80];
+        while (self.buffer.len() + padding.len()) % 128 != 112 {
+            padding.push(0);
+        }
+        padding.extend_from_slice(&bits.to_be_bytes());
+        self.update(&padding);
+
+        let mut digest = [0u8; 64];
+        for (chunk, word) in digest.chunks_exact_mut(8).zip(self.state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+
+    fn compress(&mut self, block: &[u8]) {
+        let mut w = [0u64; 80];
+        for (i, word) in block.chunks_exact(8).enumerate() {
+            w[i] = u64::from_be_bytes(word.try_into().unwrap_or_default());
+        }
+        for i in 16..80 {
+            let s0 = w[i - 15].rotate_right(1) ^ w[i - 15].rotate_right(8) ^ (w[i - 15] >> 7);
+            let s1 = w[i - 2].rotate_right(19) ^ w[i - 2].rotate_right(61) ^ (w[i - 2] >> 6);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for i in 0..80 {
+            let s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(SHA512_K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (s, v) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+}
+
+///
+/// A running hash of one of the algorithms of `DigestAlgorithm`.
+///
+#[derive(Clone, Debug)]
+pub(crate) enum Hasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+}
+
+impl Hasher {
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Sha512(hasher) => hasher.update(data),
+        }
+    }
+
+    pub(crate) fn finish(self) -> Vec<u8> {
+        match self {
+            Hasher::Sha256(hasher) => hasher.finish().to_vec(),
+            Hasher::Sha512(hasher) => hasher.finish().to_vec(),
+        }
+    }
+
+    /// Starts over, as if nothing had been hashed yet.
+    pub(crate) fn reset(&mut self) {
+        *self = match self {
+            Hasher::Sha256(_) => Hasher::Sha256(Sha256::default()),
+            Hasher::Sha512(_) => Hasher::Sha512(Sha512::default()),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::hex_encode;
+
+    // FIPS 180-2 appendix C and NIST example messages.
+    const M448: &[u8] = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+    const M896: &[u8] = b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmn\
+                          hijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu";
+
+    fn crc32(data: &[u8]) -> u32 {
+        let mut crc = Crc32::default();
+        crc.update(data);
+        crc.value()
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn crc32_chunked_update() {
+        let data: Vec<u8> = (0..=255).cycle().take(10_000).collect();
+        let mut crc = Crc32::default();
+        for chunk in data.chunks(7) {
+            crc.update(chunk);
+        }
+        assert_eq!(crc.value(), crc32(&data));
+
+        let mut crc = Crc32::default();
+        crc.update(b"1234");
+        crc.update(b"");
+        crc.update(b"56789");
+        assert_eq!(crc.value(), 0xCBF4_3926);
+    }
+
+    fn sha256(data: &[u8]) -> String {
+        let mut hasher = Sha256::default();
+        hasher.update(data);
+        hex_encode(&hasher.finish())
+    }
+
+    fn sha512(data: &[u8]) -> String {
+        let mut hasher = Sha512::default();
+        hasher.update(data);
+        hex_encode(&hasher.finish())
+    }
+
+    #[test]
+    fn sha256_vectors() {
+        assert_eq!(
+            sha256(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            sha256(M448),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+        assert_eq!(
+            sha256(M896),
+            "cf5b16a778af8380036ce59e7b0492370b249b11e8f07a51afac45037afee9d1"
+        );
+    }
+
+    #[test]
+    fn sha512_vectors() {
+        assert_eq!(
+            sha512(b""),
+            "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce\
+             47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e"
+        );
+        assert_eq!(
+            sha512(b"abc"),
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        );
+        assert_eq!(
+            sha512(M448),
+            "204a8fc6dda82f0a0ced7beb8e08a41657c16ef468b228a8279be331a703c335\
+             96fd15c13b1b07f9aa1d3bea57789ca031ad85c7a71dd70354ec631238ca3445"
+        );
+        assert_eq!(
+            sha512(M896),
+            "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018\
+             501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909"
+        );
+    }
+
+    /// Lengths around where the padding needs another block: 55/56 and 64
+    /// bytes for SHA-256, 111/112 and 128 bytes for SHA-512.
+    #[test]
+    fn padding_boundaries() {
+        let cases = [
+            (
+                55,
+                "9f4390f8d30c2dd92ec9f095b65e2b9ae9b0a925a5258e241c9f1e910f734318",
+                "b0220c772cbf6c1822e2cb38a437d0e1d58772417a4bbb21c961364f8b6143e0\
+                 5aa6316dca8d1d7b19e16448419076395f6086cb55101fbd6d5497b148e1745f",
+            ),
+            (
+                56,
+                "b35439a4ac6f0948b6d6f9e3c6af0f5f590ce20f1bde7090ef7970686ec6738a",
+                "962b64aae357d2a4fee3ded8b539bdc9d325081822b0bfc55583133aab44f18b\
+                 afe11d72a7ae16c79ce2ba620ae2242d5144809161945f1367f41b3972e26e04",
+            ),
+            (
+                64,
+                "ffe054fe7ae0cb6dc65c3af9b61d5209f439851db43d0ba5997337df154668eb",
+                "01d35c10c6c38c2dcf48f7eebb3235fb5ad74a65ec4cd016e2354c637a8fb49b\
+                 695ef3c1d6f7ae4cd74d78cc9c9bcac9d4f23a73019998a7f73038a5c9b2dbde",
+            ),
+            (
+                111,
+                "6374f73208854473827f6f6a3f43b1f53eaa3b82c21c1a6d69a2110b2a79baad",
+                "fa9121c7b32b9e01733d034cfc78cbf67f926c7ed83e82200ef8681819692176\
+                 0b4beff48404df811b953828274461673c68d04e297b0eb7b2b4d60fc6b566a2",
+            ),
+            (
+                112,
+                "f54353008a2553262ecdc4a34749563ba0950e8b0fc8652780b0a614b99683c1",
+                "c01d080efd492776a1c43bd23dd99d0a2e626d481e16782e75d54c2503b5dc32\
+                 bd05f0f1ba33e568b88fd2d970929b719ecbb152f58f130a407c8830604b70ca",
+            ),
+            (
+                128,
+                "6836cf13bac400e9105071cd6af47084dfacad4e5e302c94bfed24e013afb73e",
+                "b73d1929aa615934e61a871596b3f3b33359f42b8175602e89f7e06e5f658a24\
+                 3667807ed300314b95cacdd579f3e33abdfbe351909519a846d465c59582f321",
+            ),
+        ];
+        for (length, expected_256, expected_512) in cases {
+            let data = vec![b'a'; length];
+            assert_eq!(sha256(&data), expected_256, "SHA-256 of {} bytes", length);
+            assert_eq!(sha512(&data), expected_512, "SHA-512 of {} bytes", length);
+        }
+    }
+
+    #[test]
+    fn sha_chunked_update() {
+        let data: Vec<u8> = (0..=255).cycle().take(1_000).collect();
+        let mut hasher_256 = Sha256::default();
+        let mut hasher_512 = Sha512::default();
+        for chunk in data.chunks(13) {
+            hasher_256.update(chunk);
+            hasher_512.update(chunk);
+        }
+        assert_eq!(hex_encode(&hasher_256.finish()), sha256(&data));
+        assert_eq!(hex_encode(&hasher_512.finish()), sha512(&data));
+    }
+
+    #[test]
+    fn hasher_reset() {
+        let mut hasher = Hasher::Sha256(Sha256::default());
+        hasher.update(b"partial download");
+        hasher.reset();
+        hasher.update(b"abc");
+        assert_eq!(hex_encode(&hasher.finish()), sha256(b"abc"));
+
+        let mut hasher = Hasher::Sha512(Sha512::default());
+        hasher.update(b"partial download");
+        hasher.reset();
+        assert_eq!(hex_encode(&hasher.finish()), sha512(b""));
+    }
+}
@@ -0,0 +1,438 @@
+//! Decoders for response bodies libcurl was told to leave compressed:
+//! `gzip` (RFC 1952) and `deflate` (RFC 1950 zlib, or the bare RFC 1951
+//! stream some servers send instead), on top of an inflater.
+//!
+//! `br` and `zstd` are not decoded: both need large decoders of their own
+//! that the crate does not carry. Use `accept_encoding` with a libcurl built
+//! with brotli or zstd support for them.
+
+use crate::checksum::Crc32;
+use crate::http_client::{ContentEncoding, Error};
+
+/// Undoes one coding listed in `Content-Encoding`.
+pub(crate) fn decode(encoding: &ContentEncoding, data: &[u8]) -> Result<Vec<u8>, Error> {
+    match encoding {
+        ContentEncoding::Identity => Ok(data.to_vec()),
+        ContentEncoding::Gzip => gunzip(data),
+        ContentEncoding::Deflate => zlib_or_raw(data),
+        other => Err(Error::Other(format!("cannot decode {:?} bodies", other))),
+    }
+}
+
+fn corrupt(coding: &str) -> Error {
+    Error::Other(format!("corrupt {} body", coding))
+}
+
+/// Decodes every gzip member, as concatenated files are still one body.
+fn gunzip(mut data: &[u8]) -> Result<Vec<u8>, Error> {
+    const FHCRC: u8 = 2;
+    const FEXTRA: u8 = 4;
+    const FNAME: u8 = 8;
+    const FCOMMENT: u8 = 16;
+
+    let mut out = Vec::new();
+    loop {
+        if data.len() < 18 || data[..3] != [0x1f, 0x8b, 8] {
+            return Err(corrupt("gzip"));
+        }
+        let flags = data[3];
+        let mut pos = 10;
+        if flags & FEXTRA != 0 {
+            let len = data
+                .get(pos..pos + 2)
+                .map(|len| u16::from_le_bytes([len[0], len[1]]) as usize)
+                .ok_or_else(|| corrupt("gzip"))?;
+            pos += 2 + len;
+        }
+        for flag in [FNAME, FCOMMENT] {
+            if flags & flag != 0 {
+                let end = data
+                    .get(pos..)
+                    .and_then(|rest| rest.iter().position(|b| *b == 0))
+                    .ok_or_else(|| corrupt("gzip"))?;
+                pos += end + 1;
+            }
+        }
+        if flags & FHCRC != 0 {
+            pos += 2;
+        }
+
+        let (member, used) = inflate(data.get(pos..).ok_or_else(|| corrupt("gzip"))?)?;
+        pos += used;
+        let trailer = data.get(pos..pos + 8).ok_or_else(|| corrupt("gzip"))?;
+        let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+        let mut actual = Crc32::default();
+        actual.update(&member);
+        if actual.value() != crc || member.len() as u32 != size {
+            return Err(corrupt("gzip"));
+        }
+        out.extend_from_slice(&member);
+
+        data = &data[pos + 8..];
+        if data.is_empty() {
+            return Ok(out);
+        }
+    }
+}
+
+fn zlib_or_raw(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let zlib = data.len() >= 6
+        && data[0] & 0x0f == 8
+        && data[1] & 0x20 == 0
+        && u16::from_be_bytes([data[0], data[1]]).is_multiple_of(31);
+    if !zlib {
+        return inflate(data).map(|(out, _)| out);
+    }
+
+    let (out, used) = inflate(&data[2..])?;
+    let adler = data
+        .get(2 + used..6 + used)
+        .map(|adler| u32::from_be_bytes([adler[0], adler[1], adler[2], adler[3]]))
+        .ok_or_else(|| corrupt("deflate"))?;
+    if adler32(&out) != adler {
+        return Err(corrupt("deflate"));
+    }
+    Ok(out)
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// Order in which the code lengths of the code length alphabet are sent.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Reads the bits of a deflate stream, least significant first.
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl Bits<'_> {
+    fn take(&mut self, n: u32) -> Result<u32, Error> {
+        while self.count < n {
+            let byte = *self.data.get(self.pos).ok_or_else(|| corrupt("deflate"))?;
+            self.pos += 1;
+            self.buffer |= (byte as u32) << self.count;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1u64 << n) - 1) as u32;
+        self.buffer >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    /// Drops the rest of the current byte, as before a stored block.
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+}
+
+/// A canonical Huffman code, as the number of codes of every length and
+/// the symbols ordered by code.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16; 16];
+        for length in 1..16 {
+            offsets[length] = offsets[length - 1] + counts[length - 1];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length != 0 {
+                symbols[offsets[*length as usize] as usize] = symbol as u16;
+                offsets[*length as usize] += 1;
+            }
+        }
+        Self { counts, symbols }
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Result<u16, Error> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..16 {
+            code |= bits.take(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(corrupt("deflate"))
+    }
+}
+
+/// Inflates a bare deflate stream, returning the data and the number of
+/// bytes the stream took up.
+fn inflate(data: &[u8]) -> Result<(Vec<u8>, usize), Error> {
+    let mut bits = Bits {
+        data,
+        pos: 0,
+        buffer: 0,
+        count: 0,
+    };
+    let mut out = Vec::new();
+    loop {
+        let last = bits.take(1)? == 1;
+        match bits.take(2)? {
+            0 => {
+                bits.align();
+                let header = data
+                    .get(bits.pos..bits.pos + 4)
+                    .ok_or_else(|| corrupt("deflate"))?;
+                let len = u16::from_le_bytes([header[0], header[1]]);
+                if len != !u16::from_le_bytes([header[2], header[3]]) {
+                    return Err(corrupt("deflate"));
+                }
+                let start = bits.pos + 4;
+                let stored = data
+                    .get(start..start + len as usize)
+                    .ok_or_else(|| corrupt("deflate"))?;
+                out.extend_from_slice(stored);
+                bits.pos = start + len as usize;
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let literals = Huffman::new(&lengths);
+                let distances = Huffman::new(&[5; 30]);
+                inflate_block(&mut bits, &mut out, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut bits)?;
+                inflate_block(&mut bits, &mut out, &literals, &distances)?;
+            }
+            _ => return Err(corrupt("deflate")),
+        }
+        if last {
+            return Ok((out, bits.pos));
+        }
+    }
+}
+
+fn dynamic_codes(bits: &mut Bits) -> Result<(Huffman, Huffman), Error> {
+    let literal_count = bits.take(5)? as usize + 257;
+    let distance_count = bits.take(5)? as usize + 1;
+    let code_length_count = bits.take(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for index in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        code_lengths[*index] = bits.take(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (length, repeat) = match code_lengths.decode(bits)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (
+                *lengths.last().ok_or_else(|| corrupt("deflate"))?,
+                3 + bits.take(2)?,
+            ),
+            17 => (0, 3 + bits.take(3)?),
+            _ => (0, 11 + bits.take(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(length, repeat as usize));
+    }
+    if lengths.len() != literal_count + distance_count {
+        return Err(corrupt("deflate"));
+    }
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+fn inflate_block(
+    bits: &mut Bits,
+    out: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), Error> {
+    loop {
+        let symbol = literals.decode(bits)? as usize;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let index = symbol - 257;
+                if index >= LENGTH_BASE.len() {
+                    return Err(corrupt("deflate"));
+                }
+                let length =
+                    LENGTH_BASE[index] as usize + bits.take(LENGTH_EXTRA[index] as u32)? as usize;
+                let index = distances.decode(bits)? as usize;
+                if index >= DISTANCE_BASE.len() {
+                    return Err(corrupt("deflate"));
+                }
+                let distance = DISTANCE_BASE[index] as usize
+                    + bits.take(DISTANCE_EXTRA[index] as u32)? as usize;
+                if distance > out.len() {
+                    return Err(corrupt("deflate"));
+                }
+                // The copy may overlap the bytes it produces.
+                let start = out.len() - distance;
+                for i in 0..length {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_client::tests::test_response;
+
+    // Produced with Python's `gzip.compress(data, mtime=0)` and
+    // `zlib.compressobj(level, zlib.DEFLATED, wbits)`.
+    /// `hello, world\n`, fixed Huffman codes.
+    const GZIP_HELLO: &[u8] = &[
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xcb, 0x48, 0xcd, 0xc9, 0xc9,
+        0xd7, 0x51, 0x28, 0xcf, 0x2f, 0xca, 0x49, 0xe1, 0x02, 0x00, 0x53, 0x74, 0x24, 0xf4, 0x0d,
+        0x00, 0x00, 0x00,
+    ];
+    /// `text()`, dynamic Huffman codes.
+    const ZLIB_TEXT: &[u8] = &[
+        0x78, 0xda, 0x9d, 0xd2, 0x5d, 0x16, 0x42, 0x50, 0x18, 0x85, 0xe1, 0x7b, 0xa3, 0xf8, 0x86,
+        0x60, 0x4b, 0x3f, 0x9a, 0x8d, 0x38, 0x4a, 0x0e, 0x27, 0x0a, 0x65, 0xf4, 0x96, 0x66, 0xe0,
+        0xbd, 0xde, 0xeb, 0xbd, 0xda, 0x8f, 0xaf, 0x3b, 0x67, 0xf1, 0xd5, 0x3e, 0x0f, 0x67, 0xfd,
+        0x58, 0x17, 0x8d, 0xdd, 0x86, 0x30, 0x77, 0x56, 0x85, 0xaf, 0x3d, 0xc7, 0xf6, 0xf5, 0xb6,
+        0x30, 0xb9, 0xe1, 0x3f, 0xfb, 0x7c, 0xf9, 0x59, 0x19, 0xee, 0x91, 0xdf, 0x1a, 0x81, 0x26,
+        0x01, 0xcd, 0x01, 0x34, 0x29, 0x68, 0x8e, 0xa0, 0x39, 0x81, 0xe6, 0x0c, 0x9a, 0x0b, 0x68,
+        0x32, 0xf2, 0x29, 0x82, 0x40, 0x24, 0x88, 0x50, 0x10, 0xb1, 0x20, 0x82, 0x41, 0x44, 0x83,
+        0x08, 0x07, 0x11, 0x0f, 0x22, 0x20, 0xb4, 0x53, 0xc4, 0x0a, 0xaf, 0x3e, 0x70, 0xf8,
+    ];
+    /// `text()` without the zlib wrapper.
+    const RAW_DEFLATE_TEXT: &[u8] = &[
+        0x9d, 0xd2, 0x5d, 0x16, 0x42, 0x50, 0x18, 0x85, 0xe1, 0x7b, 0xa3, 0xf8, 0x86, 0x60, 0x4b,
+        0x3f, 0x9a, 0x8d, 0x38, 0x4a, 0x0e, 0x27, 0x0a, 0x65, 0xf4, 0x96, 0x66, 0xe0, 0xbd, 0xde,
+        0xeb, 0xbd, 0xda, 0x8f, 0xaf, 0x3b, 0x67, 0xf1, 0xd5, 0x3e, 0x0f, 0x67, 0xfd, 0x58, 0x17,
+        0x8d, 0xdd, 0x86, 0x30, 0x77, 0x56, 0x85, 0xaf, 0x3d, 0xc7, 0xf6, 0xf5, 0xb6, 0x30, 0xb9,
+        0xe1, 0x3f, 0xfb, 0x7c, 0xf9, 0x59, 0x19, 0xee, 0x91, 0xdf, 0x1a, 0x81, 0x26, 0x01, 0xcd,
+        0x01, 0x34, 0x29, 0x68, 0x8e, 0xa0, 0x39, 0x81, 0xe6, 0x0c, 0x9a, 0x0b, 0x68, 0x32, 0xf2,
+        0x29, 0x82, 0x40, 0x24, 0x88, 0x50, 0x10, 0xb1, 0x20, 0x82, 0x41, 0x44, 0x83, 0x08, 0x07,
+        0x11, 0x0f, 0x22, 0x20, 0xb4, 0x53, 0xc4, 0x0a,
+    ];
+    /// `hello, world\n` in a stored block.
+    const ZLIB_STORED_HELLO: &[u8] = &[
+        0x78, 0x01, 0x01, 0x0d, 0x00, 0xf2, 0xff, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x2c, 0x20, 0x77,
+        0x6f, 0x72, 0x6c, 0x64, 0x0a, 0x21, 0xe7, 0x04, 0x93,
+    ];
+
+    fn text() -> Vec<u8> {
+        (0..20)
+            .map(|i| format!("line {}: the quick brown fox jumps over the lazy dog\n", i))
+            .collect::<String>()
+            .into_bytes()
+    }
+
+    #[test]
+    fn gzip() {
+        let decoded = decode(&ContentEncoding::Gzip, GZIP_HELLO).unwrap();
+        assert_eq!(decoded, b"hello, world\n");
+    }
+
+    #[test]
+    fn gzip_members() {
+        let data = [GZIP_HELLO, GZIP_HELLO].concat();
+        let decoded = decode(&ContentEncoding::Gzip, &data).unwrap();
+        assert_eq!(decoded, b"hello, world\nhello, world\n");
+    }
+
+    #[test]
+    fn gzip_bad_crc() {
+        let mut data = GZIP_HELLO.to_vec();
+        let crc = data.len() - 8;
+        data[crc] ^= 1;
+        assert!(decode(&ContentEncoding::Gzip, &data).is_err());
+    }
+
+    #[test]
+    fn zlib() {
+        assert_eq!(
+            decode(&ContentEncoding::Deflate, ZLIB_TEXT).unwrap(),
+            text()
+        );
+        assert_eq!(
+            decode(&ContentEncoding::Deflate, ZLIB_STORED_HELLO).unwrap(),
+            b"hello, world\n"
+        );
+    }
+
+    #[test]
+    fn raw_deflate() {
+        assert_eq!(
+            decode(&ContentEncoding::Deflate, RAW_DEFLATE_TEXT).unwrap(),
+            text()
+        );
+    }
+
+    #[test]
+    fn truncated() {
+        let data = &ZLIB_TEXT[..ZLIB_TEXT.len() / 2];
+        assert!(decode(&ContentEncoding::Deflate, data).is_err());
+    }
+
+    #[test]
+    fn unsupported() {
+        assert!(decode(&ContentEncoding::Brotli, GZIP_HELLO).is_err());
+        assert!(decode(&ContentEncoding::Zstd, GZIP_HELLO).is_err());
+    }
+
+    #[test]
+    fn decompressed_body() {
+        let response = test_response(200, &[("content-encoding", "gzip")], GZIP_HELLO);
+        assert_eq!(&*response.decompressed_body().unwrap(), b"hello, world\n");
+
+        let response = test_response(200, &[("content-encoding", "identity")], b"plain");
+        assert_eq!(&*response.decompressed_body().unwrap(), b"plain");
+
+        let response = test_response(200, &[("content-encoding", "br")], GZIP_HELLO);
+        assert!(response.decompressed_body().is_err());
+    }
+}
@@ -0,0 +1,50 @@
+use std::time::{Duration, SystemTime};
+
+///
+/// Cookie as stored by libcurl's cookie engine.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cookie {
+    pub domain: String,
+    pub include_subdomains: bool,
+    pub path: String,
+    pub secure: bool,
+    pub http_only: bool,
+    /// `None` for session cookies.
+    pub expires: Option<SystemTime>,
+    pub name: String,
+    pub value: String,
+}
+
+impl Cookie {
+    /// Parses one line of libcurl's Netscape cookie file format:
+    /// `domain \t subdomains \t path \t secure \t expiry \t name \t value`,
+    /// where HttpOnly cookies have their domain prefixed with `#HttpOnly_`.
+    pub fn from_netscape(line: &str) -> Option<Self> {
+        let mut fields = line.trim_end_matches(['\r', '\n']).split('\t');
+        let domain = fields.next()?;
+        let (domain, http_only) = match domain.strip_prefix("#HttpOnly_") {
+            Some(domain) => (domain, true),
+            None => (domain, false),
+        };
+        let include_subdomains = fields.next()? == "TRUE";
+        let path = fields.next()?.to_string();
+        let secure = fields.next()? == "TRUE";
+        let expires = match fields.next()?.parse::<u64>().ok()? {
+            0 => None,
+            secs => Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs)),
+        };
+        let name = fields.next()?.to_string();
+        let value = fields.next().unwrap_or_default().to_string();
+        Some(Self {
+            domain: domain.to_string(),
+            include_subdomains,
+            path,
+            secure,
+            http_only,
+            expires,
+            name,
+            value,
+        })
+    }
+}
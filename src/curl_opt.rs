@@ -0,0 +1,130 @@
+//! Setters for libcurl options that the `curl` crate does not wrap yet.
+
+use std::ffi::CString;
+use std::os::raw::{c_char, c_long};
+
+use curl::easy::Easy2;
+use curl_sys::{CURLoption, CURLOPTTYPE_LONG, CURLOPTTYPE_OBJECTPOINT};
+
+// Options missing from `curl_sys`, numbered as in libcurl's `curl.h`.
+// `curl_sys` lists this one commented out, so it cannot be imported either;
+// `CURLOPTTYPE_STRINGPOINT` in `curl.h` is the same as `CURLOPTTYPE_OBJECTPOINT`.
+pub(crate) const CURLOPT_XOAUTH2_BEARER: CURLoption = CURLOPTTYPE_OBJECTPOINT + 220;
+pub(crate) const CURLOPT_SSL_ENABLE_ALPN: CURLoption = CURLOPTTYPE_LONG + 226;
+#[cfg(feature = "kerberos")]
+pub(crate) const CURLOPT_SERVICE_NAME: CURLoption = CURLOPTTYPE_OBJECTPOINT + 236;
+pub(crate) const CURLOPT_STREAM_WEIGHT: CURLoption = CURLOPTTYPE_LONG + 239;
+pub(crate) const CURLOPT_TCP_FASTOPEN: CURLoption = CURLOPTTYPE_LONG + 244;
+pub(crate) const CURLOPT_PROXY_PINNEDPUBLICKEY: CURLoption = CURLOPTTYPE_OBJECTPOINT + 263;
+pub(crate) const CURLOPT_SUPPRESS_CONNECT_HEADERS: CURLoption = CURLOPTTYPE_LONG + 265;
+#[cfg(feature = "haproxy")]
+pub(crate) const CURLOPT_HAPROXYPROTOCOL: CURLoption = CURLOPTTYPE_LONG + 274;
+pub(crate) const CURLOPT_UPKEEP_INTERVAL_MS: CURLoption = CURLOPTTYPE_LONG + 281;
+pub(crate) const CURLOPT_SASL_AUTHZID: CURLoption = CURLOPTTYPE_OBJECTPOINT + 289;
+pub(crate) const CURLOPT_SSH_HOST_PUBLIC_KEY_SHA256: CURLoption = CURLOPTTYPE_OBJECTPOINT + 311;
+
+pub(crate) fn setopt_str<H>(
+    easy: &mut Easy2<H>,
+    option: CURLoption,
+    value: &str,
+) -> Result<(), curl::Error> {
+    let value =
+        CString::new(value).map_err(|_| curl::Error::new(curl_sys::CURLE_BAD_FUNCTION_ARGUMENT))?;
+    // SAFETY: `option` takes a `char *`, which libcurl copies before returning.
+    let code = unsafe { curl_sys::curl_easy_setopt(easy.raw(), option, value.as_ptr()) };
+    cvt(code)
+}
+
+/// Sets a string option back to its default, which the `curl` crate cannot
+/// do as its setters take a `&str`.
+pub(crate) fn unset_str<H>(easy: &mut Easy2<H>, option: CURLoption) -> Result<(), curl::Error> {
+    // SAFETY: `option` takes a `char *`, for which NULL restores the default.
+    let code =
+        unsafe { curl_sys::curl_easy_setopt(easy.raw(), option, std::ptr::null::<c_char>()) };
+    cvt(code)
+}
+
+pub(crate) fn setopt_long<H>(
+    easy: &mut Easy2<H>,
+    option: CURLoption,
+    value: c_long,
+) -> Result<(), curl::Error> {
+    // SAFETY: `option` takes a `long`.
+    let code = unsafe { curl_sys::curl_easy_setopt(easy.raw(), option, value) };
+    cvt(code)
+}
+
+/// A `curl_slist` owned by the client, which has to outlive every transfer
+/// of the handle it was set on.
+#[derive(Debug)]
+pub(crate) struct StringList(*mut curl_sys::curl_slist);
+
+// SAFETY: the list is only read by libcurl during transfers of the handle
+// owning it, never shared.
+unsafe impl Send for StringList {}
+
+impl StringList {
+    pub(crate) fn new<'a>(items: impl IntoIterator<Item = &'a str>) -> Result<Self, curl::Error> {
+        let mut list = StringList(std::ptr::null_mut());
+        for item in items {
+            let item = CString::new(item)
+                .map_err(|_| curl::Error::new(curl_sys::CURLE_BAD_FUNCTION_ARGUMENT))?;
+            // SAFETY: libcurl copies `item`; on failure the old list is kept.
+            let raw = unsafe { curl_sys::curl_slist_append(list.0, item.as_ptr()) };
+            if raw.is_null() {
+                return Err(curl::Error::new(curl_sys::CURLE_OUT_OF_MEMORY));
+            }
+            list.0 = raw;
+        }
+        Ok(list)
+    }
+}
+
+impl Drop for StringList {
+    fn drop(&mut self) {
+        // SAFETY: the list was built by `curl_slist_append` and is owned.
+        unsafe { curl_sys::curl_slist_free_all(self.0) }
+    }
+}
+
+pub(crate) fn setopt_list<H>(
+    easy: &mut Easy2<H>,
+    option: CURLoption,
+    list: &StringList,
+) -> Result<(), curl::Error> {
+    // SAFETY: `option` takes a `struct curl_slist *`, which the caller keeps
+    // alive for as long as the handle uses it.
+    let code = unsafe { curl_sys::curl_easy_setopt(easy.raw(), option, list.0) };
+    cvt(code)
+}
+
+/// The handle a handler is attached to, so its callbacks can act on their
+/// own transfer.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RawEasy(*mut curl_sys::CURL);
+
+// SAFETY: only used from callbacks of the handle, on the thread driving it.
+unsafe impl Send for RawEasy {}
+
+impl RawEasy {
+    pub(crate) fn new<H>(easy: &Easy2<H>) -> Self {
+        RawEasy(easy.raw())
+    }
+
+    /// Resumes receiving after `write` returned `WriteError::Pause`. Only
+    /// call it from within a callback of the same handle: libcurl hands the
+    /// data it held back to `write` again before returning.
+    pub(crate) fn unpause_write(self) -> Result<(), curl::Error> {
+        // SAFETY: the handle outlives the callbacks of its handler.
+        let code = unsafe { curl_sys::curl_easy_pause(self.0, curl_sys::CURLPAUSE_RECV_CONT) };
+        cvt(code)
+    }
+}
+
+fn cvt(code: curl_sys::CURLcode) -> Result<(), curl::Error> {
+    if code == curl_sys::CURLE_OK {
+        Ok(())
+    } else {
+        Err(curl::Error::new(code))
+    }
+}
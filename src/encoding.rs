@@ -0,0 +1,135 @@
+//! Base64 (RFC 4648, standard alphabet) used by header values, hex for
+//! checksums, and random UUIDs for request ids.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub(crate) fn base64_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decodes padded base64, returning `None` on invalid input, which includes
+/// a length that is not a multiple of 4, more than two padding characters
+/// and set bits after the last encoded byte.
+pub(crate) fn base64_decode(encoded: &str) -> Option<Vec<u8>> {
+    if !encoded.len().is_multiple_of(4) {
+        return None;
+    }
+    let data = encoded.trim_end_matches('=');
+    if encoded.len() - data.len() > 2 {
+        return None;
+    }
+    let mut decoded = Vec::with_capacity(data.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in data.bytes() {
+        let value = ALPHABET.iter().position(|a| *a == c)? as u32;
+        buffer = (buffer << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    // Encoders leave the bits after the last byte zero, so other encodings
+    // of the same bytes are not accepted as equal.
+    (buffer == 0).then_some(decoded)
+}
+
+/// Lowercase hex, as checksums are usually published.
+pub(crate) fn hex_encode(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// A version 4 UUID, e.g. `0c5b2d1e-...`, from the randomly keyed hasher
+/// of the standard library. Unique enough for ids, not for secrets.
+pub(crate) fn random_uuid() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let state = RandomState::new();
+    let mut bytes = [0u8; 16];
+    for (half, chunk) in bytes.chunks_mut(8).enumerate() {
+        let mut hasher = state.build_hasher();
+        hasher.write_u128(nanos);
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        hasher.write_usize(half);
+        chunk.copy_from_slice(&hasher.finish().to_be_bytes());
+    }
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex_encode(&bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_round_trip() {
+        for data in [&b""[..], b"f", b"fo", b"foo", b"foob", b"fooba", b"foobar"] {
+            let encoded = base64_encode(data);
+            assert_eq!(base64_decode(&encoded).as_deref(), Some(data));
+        }
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_decode("QQ==").as_deref(), Some(&b"A"[..]));
+    }
+
+    #[test]
+    fn base64_rejects_bad_padding() {
+        assert_eq!(base64_decode("Q==="), None);
+        assert_eq!(base64_decode("===="), None);
+        assert_eq!(base64_decode("QQ=A"), None);
+    }
+
+    #[test]
+    fn base64_rejects_wrong_length() {
+        assert_eq!(base64_decode("QQ"), None);
+        assert_eq!(base64_decode("QUJ"), None);
+        assert_eq!(base64_decode("QUJDRA="), None);
+    }
+
+    #[test]
+    fn base64_rejects_trailing_bits() {
+        assert_eq!(base64_decode("QR=="), None);
+        assert_eq!(base64_decode("QUE="), Some(b"AA".to_vec()));
+        assert_eq!(base64_decode("QUF="), None);
+    }
+
+    #[test]
+    fn base64_rejects_foreign_characters() {
+        assert_eq!(base64_decode("Zm9v Ym="), None);
+        assert_eq!(base64_decode("Zm9v-mFy"), None);
+    }
+}
@@ -0,0 +1,265 @@
+//! FTP transfers on top of `HttpClient`.
+
+use std::path::PathBuf;
+
+use curl::easy::{Easy2, Handler};
+use url::Url;
+
+use crate::http_client::{
+    Build, DownloadHandler, Error, ExtendedHandler, HttpClient, HttpRequestBuilder, HttpResponse,
+    InMemoryHandler, UseSsl,
+};
+
+///
+/// Client for `ftp://` and `ftps://` URLs. Options shared with HTTP, such as
+/// timeouts, proxies or CA certificates, are set on the `HttpClient` it is
+/// created from.
+///
+/// `HttpResponse::status_code` holds the last FTP reply code, e.g. `226`
+/// after a completed transfer. Passive data connections go to the address
+/// of the control connection, see `HttpClient::ftp_skip_pasv_ip`.
+///
+#[derive(Debug)]
+pub struct FtpClient<H: ExtendedHandler> {
+    client: HttpClient<H, Build>,
+}
+
+impl<H: ExtendedHandler> From<HttpClient<H, Build>> for FtpClient<H> {
+    fn from(mut client: HttpClient<H, Build>) -> Self {
+        if let Err(e) = client.set_ftp_skip_pasv_ip(true) {
+            println!("Warning! Cannot skip the PASV address: {}", e);
+        }
+        Self { client }
+    }
+}
+
+impl<H: ExtendedHandler> FtpClient<H> {
+    pub fn new(easy: Easy2<H>) -> Self {
+        HttpClient::new(easy).into()
+    }
+
+    /// Upgrades the control connection with `AUTH TLS` on `ftp://` URLs.
+    /// `UseSsl::Try` silently stays in cleartext if the server does not
+    /// support TLS, use `UseSsl::Control` or `UseSsl::All` to require it.
+    pub fn opportunistic_tls(mut self, use_ssl: UseSsl) -> Result<Self, Error> {
+        self.client = self.client.use_ssl(use_ssl)?;
+        Ok(self)
+    }
+
+    /// Runs `commands` before the transfer, see `HttpClient::ftp_pre_quote`.
+    pub fn pre_quote(mut self, commands: Vec<String>) -> Result<Self, Error> {
+        self.client = self.client.ftp_pre_quote(commands)?;
+        Ok(self)
+    }
+
+    /// Sends `commands` as `SITE` commands right before the transfer, see
+    /// `HttpClient::ftp_prequote_as_site`.
+    pub fn site_commands(mut self, commands: Vec<String>) -> Result<Self, Error> {
+        self.client = self.client.ftp_prequote_as_site(commands)?;
+        Ok(self)
+    }
+
+    /// Runs `commands` once the transfer has completed, see
+    /// `HttpClient::ftp_post_quote`.
+    pub fn post_quote(mut self, commands: Vec<String>) -> Result<Self, Error> {
+        self.client = self.client.ftp_post_quote(commands)?;
+        Ok(self)
+    }
+
+    /// Downloads the file at `url` into the client's handler.
+    pub async fn download(self, url: Url) -> Result<HttpResponse, Error> {
+        self.client
+            .exchange(HttpRequestBuilder::new(url).build())
+            .await
+    }
+}
+
+impl FtpClient<InMemoryHandler> {
+    /// Downloads every file matching the last path segment of `url`, a
+    /// pattern such as `ftp://server/logs/*.log` (`*`, `?` and `[a-z]`), each
+    /// into the handler `handler_for` returns for its name. Returns the
+    /// matched names with the paths they were saved to.
+    ///
+    /// The directory is listed and the files are fetched one after the other
+    /// over this client's handle, so TLS, timeouts, proxy, CA and quote
+    /// settings apply to every transfer. Each file is held in memory until
+    /// it has been written to its handler.
+    pub async fn download_glob<F>(
+        self,
+        url: Url,
+        mut handler_for: F,
+    ) -> Result<Vec<(String, PathBuf)>, Error>
+    where
+        F: FnMut(&str) -> Result<DownloadHandler, Error>,
+    {
+        let pattern = url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .map(percent_decode)
+            .filter(|segment| !segment.is_empty())
+            .ok_or_else(|| Error::Other(format!("{} has no file name pattern", url)))?;
+        let directory = url.join("./").map_err(Error::ParseError)?;
+
+        let (client, listing) = self
+            .client
+            .list_only(true)?
+            .request(HttpRequestBuilder::new(directory.clone()).build())?
+            .perform_and_keep()
+            .await?;
+        let names = String::from_utf8_lossy(&listing.body)
+            .lines()
+            .map(str::trim)
+            .filter(|name| !name.is_empty() && glob_match(&pattern, name))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+
+        let mut client = client.list_only(false)?;
+        let mut downloads = Vec::with_capacity(names.len());
+        for name in names {
+            let mut handler = handler_for(&name)?;
+            let file_url = directory.join(&name).map_err(Error::ParseError)?;
+            let response;
+            (client, response) = client
+                .request(HttpRequestBuilder::new(file_url).build())?
+                .perform_and_keep()
+                .await?;
+            handler
+                .write(&response.body)
+                .map_err(|_| Error::Other(format!("cannot write {}", handler.path().display())))?;
+            handler.verify()?;
+            downloads.push((name, handler.path().to_path_buf()));
+        }
+        Ok(downloads)
+    }
+}
+
+/// Decodes `%XX` escapes, so that the pattern matches listed names.
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Matches `name` against a libcurl style wildcard pattern: `*` for any
+/// run of characters, `?` for one, and `[...]` for a set with ranges,
+/// negated by a leading `!` or `^`.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    // Backtracking over the last `*`, which is enough for every pattern.
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+            continue;
+        }
+        if p < pattern.len() {
+            if let Some(next) = match_one(&pattern[p..], name[n]) {
+                p += next;
+                n += 1;
+                continue;
+            }
+        }
+        match star {
+            Some((star_p, star_n)) => {
+                p = star_p + 1;
+                n = star_n + 1;
+                star = Some((star_p, star_n + 1));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Matches `c` against the pattern element at the start of `pattern`,
+/// returning how many pattern characters it spans.
+fn match_one(pattern: &[char], c: char) -> Option<usize> {
+    match pattern[0] {
+        '?' => Some(1),
+        '[' => {
+            // An unclosed `[` is an ordinary character.
+            let Some(end) = pattern.iter().skip(2).position(|p| *p == ']') else {
+                return (c == '[').then_some(1);
+            };
+            let end = end + 2;
+            let mut set = &pattern[1..end];
+            let negated = matches!(set.first(), Some('!' | '^'));
+            if negated {
+                set = &set[1..];
+            }
+            let mut found = false;
+            let mut i = 0;
+            while i < set.len() {
+                if i + 2 < set.len() && set[i + 1] == '-' {
+                    found |= (set[i]..=set[i + 2]).contains(&c);
+                    i += 3;
+                } else {
+                    found |= set[i] == c;
+                    i += 1;
+                }
+            }
+            (found != negated).then_some(end + 1)
+        }
+        literal => (literal == c).then_some(1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        assert!(glob_match("*.txt", "notes.txt"));
+        assert!(glob_match("*.txt", ".txt"));
+        assert!(!glob_match("*.txt", "notes.txt.gz"));
+        assert!(glob_match("report-??.csv", "report-01.csv"));
+        assert!(!glob_match("report-??.csv", "report-1.csv"));
+        assert!(glob_match("*a*b*", "xxaxxbxx"));
+        assert!(!glob_match("*a*b*", "xxbxxaxx"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("?", ""));
+        assert!(glob_match("exact", "exact"));
+        assert!(!glob_match("exact", "exactly"));
+    }
+
+    #[test]
+    fn wildcard_sets() {
+        assert!(glob_match("file[0-9].log", "file7.log"));
+        assert!(!glob_match("file[0-9].log", "filex.log"));
+        assert!(glob_match("[abc]*", "banana"));
+        assert!(glob_match("[!abc]*", "delta"));
+        assert!(!glob_match("[^abc]*", "alpha"));
+        assert!(glob_match("*[a-cx-z]", "fox"));
+        // An unclosed `[` is an ordinary character.
+        assert!(glob_match("a[b", "a[b"));
+        assert!(!glob_match("a[b", "ab"));
+    }
+
+    #[test]
+    fn percent_escapes() {
+        assert_eq!(percent_decode("my%20file%2A.txt"), "my file*.txt");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+    }
+}
@@ -0,0 +1,368 @@
+//! Parsers for HTTP header values.
+
+use std::time::{Duration, SystemTime};
+
+///
+/// A single link of a `Link` header (RFC 8288).
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinkHeader {
+    /// The URI-reference between `<` and `>`, not resolved against any base.
+    pub uri: String,
+    /// Link parameters in order of appearance, with lowercase names and
+    /// unquoted values.
+    pub params: Vec<(String, String)>,
+}
+
+impl LinkHeader {
+    /// Value of the first parameter called `name` (case-insensitive).
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The `rel` parameter, which may hold several space separated types.
+    pub fn rel(&self) -> Option<&str> {
+        self.param("rel")
+    }
+
+    /// Whether `rel` contains `relation_type` (case-insensitive).
+    pub fn has_rel(&self, relation_type: &str) -> bool {
+        self.rel().is_some_and(|rel| {
+            rel.split_ascii_whitespace()
+                .any(|rel| rel.eq_ignore_ascii_case(relation_type))
+        })
+    }
+}
+
+/// Parses the value of a `Link` header into its links, in order.
+///
+/// Links are separated by commas outside of `<...>` and quoted strings.
+/// Quoted parameter values are unescaped, and parameters without a value
+/// get an empty one. Malformed trailing input is ignored.
+pub fn parse_link_header(value: &str) -> Vec<LinkHeader> {
+    let mut links = Vec::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('<') {
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let uri = rest[start + 1..start + end].trim().to_string();
+        rest = &rest[start + end + 1..];
+
+        let mut params = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            let Some(after_semicolon) = rest.strip_prefix(';') else {
+                break;
+            };
+            let param_end = after_semicolon
+                .find(['=', ';', ','])
+                .unwrap_or(after_semicolon.len());
+            let name = after_semicolon[..param_end].trim().to_ascii_lowercase();
+            rest = &after_semicolon[param_end..];
+            let value = match rest.strip_prefix('=') {
+                Some(after_equals) => {
+                    let (value, after_value) = split_param_value(after_equals.trim_start());
+                    rest = after_value;
+                    value
+                }
+                None => String::new(),
+            };
+            if !name.is_empty() {
+                params.push((name, value));
+            }
+        }
+        links.push(LinkHeader { uri, params });
+
+        match rest.find(',') {
+            Some(comma) => rest = &rest[comma + 1..],
+            None => break,
+        }
+    }
+    links
+}
+
+/// Splits a token or quoted-string parameter value off the front of `value`.
+fn split_param_value(value: &str) -> (String, &str) {
+    if value.starts_with('"') {
+        split_quoted(value)
+    } else {
+        let end = value.find([';', ',']).unwrap_or(value.len());
+        (value[..end].trim().to_string(), &value[end..])
+    }
+}
+
+/// Splits a leading quoted-string off `value`, unescaping `\"` pairs.
+/// Returns an empty string if `value` does not start with a quote.
+pub(crate) fn split_quoted(value: &str) -> (String, &str) {
+    let Some(quoted) = value.strip_prefix('"') else {
+        return (String::new(), value);
+    };
+    let mut text = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some((_, escaped)) = chars.next() {
+                    text.push(escaped);
+                }
+            }
+            '"' => return (text, &quoted[i + 1..]),
+            c => text.push(c),
+        }
+    }
+    (text, "")
+}
+
+/// Value of the parameter `name` of a media type such as
+/// `multipart/byteranges; boundary="abc"`, unquoted.
+pub(crate) fn media_type_param(value: &str, name: &str) -> Option<String> {
+    let mut rest = value.split_once(';')?.1;
+    loop {
+        let (key, after_key) = rest.split_once('=')?;
+        let after_key = after_key.trim_start();
+        let (param, after_param) = if after_key.starts_with('"') {
+            split_quoted(after_key)
+        } else {
+            let end = after_key.find(';').unwrap_or(after_key.len());
+            (after_key[..end].trim().to_string(), &after_key[end..])
+        };
+        if key.trim().eq_ignore_ascii_case(name) {
+            return Some(param);
+        }
+        rest = after_param.split_once(';')?.1;
+    }
+}
+
+/// First and last byte position of a `Content-Range: bytes <first>-<last>/<length>`
+/// value, the length being ignored.
+pub(crate) fn parse_content_range(value: &str) -> Option<(u64, u64)> {
+    let (unit, range) = value.trim().split_once(' ')?;
+    if !unit.eq_ignore_ascii_case("bytes") {
+        return None;
+    }
+    let (first, last) = range.split_once('/')?.0.split_once('-')?;
+    let (first, last) = (first.trim().parse().ok()?, last.trim().parse().ok()?);
+    (first <= last).then_some((first, last))
+}
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Parses an HTTP-date (RFC 9110 §5.6.7): the preferred IMF-fixdate
+/// `Sun, 06 Nov 1994 08:49:37 GMT` as well as the obsolete RFC 850
+/// `Sunday, 06-Nov-94 08:49:37 GMT` and asctime `Sun Nov  6 08:49:37 1994`
+/// forms.
+pub(crate) fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let value = value.trim();
+    let (day, month, year, time) = match value.split_once(',') {
+        Some((_, rest)) => {
+            let fields = rest
+                .split([' ', '-'])
+                .filter(|field| !field.is_empty())
+                .collect::<Vec<_>>();
+            match fields[..] {
+                [day, month, year, time, "GMT"] => (day, month, year, time),
+                _ => return None,
+            }
+        }
+        None => match value.split_ascii_whitespace().collect::<Vec<_>>()[..] {
+            [_, month, day, time, year] => (day, month, year, time),
+            _ => return None,
+        },
+    };
+
+    let day = day
+        .parse::<u32>()
+        .ok()
+        .filter(|day| (1..=31).contains(day))?;
+    let month = MONTHS.iter().position(|name| *name == month)? as u32 + 1;
+    let year = match (year.len(), year.parse::<i64>().ok()?) {
+        // RFC 850 two-digit years, read as the closest year in the past.
+        (2, year) if year < 70 => 2000 + year,
+        (2, year) => 1900 + year,
+        (4, year) => year,
+        _ => return None,
+    };
+    let time = match time
+        .split(':')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?[..]
+    {
+        [hours, minutes, seconds] if hours < 24 && minutes < 60 && seconds <= 60 => {
+            hours * 3600 + minutes * 60 + seconds
+        }
+        _ => return None,
+    };
+
+    let seconds = days_from_civil(year, month, day) * 86400 + time as i64;
+    if seconds >= 0 {
+        SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(seconds as u64))
+    } else {
+        SystemTime::UNIX_EPOCH.checked_sub(Duration::from_secs(seconds.unsigned_abs()))
+    }
+}
+
+/// Days between 1970-01-01 and the given date of the proleptic Gregorian
+/// calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// `Content-Type` commonly used for files with extension `extension`
+/// (case-insensitive), for the formats a client usually uploads.
+pub(crate) fn content_type_for_extension(extension: &str) -> Option<&'static str> {
+    let content_type = match extension.to_ascii_lowercase().as_str() {
+        "txt" | "text" | "log" => "text/plain; charset=utf-8",
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "js" | "mjs" => "text/javascript",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "yaml" | "yml" => "application/yaml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "tar" => "application/x-tar",
+        "wasm" => "application/wasm",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "ico" => "image/vnd.microsoft.icon",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "bin" => "application/octet-stream",
+        _ => return None,
+    };
+    Some(content_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(uri: &str, params: &[(&str, &str)]) -> LinkHeader {
+        LinkHeader {
+            uri: uri.to_string(),
+            params: params
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn link_header() {
+        let links = parse_link_header(
+            r#"<https://api.example/items?page=2>; rel="next", <https://api.example/items?page=9>; rel=last"#,
+        );
+        assert_eq!(
+            links,
+            vec![
+                link("https://api.example/items?page=2", &[("rel", "next")]),
+                link("https://api.example/items?page=9", &[("rel", "last")]),
+            ]
+        );
+        assert!(links[0].has_rel("NEXT"));
+        assert!(!links[0].has_rel("last"));
+    }
+
+    #[test]
+    fn link_header_quoting() {
+        let links = parse_link_header(
+            r#"</a,b>; REL="preload prefetch"; title="say \"hi\", then go"; crossorigin, </c>"#,
+        );
+        assert_eq!(
+            links,
+            vec![
+                link(
+                    "/a,b",
+                    &[
+                        ("rel", "preload prefetch"),
+                        ("title", r#"say "hi", then go"#),
+                        ("crossorigin", ""),
+                    ]
+                ),
+                link("/c", &[]),
+            ]
+        );
+        assert!(links[0].has_rel("prefetch"));
+        assert_eq!(links[0].param("Title"), Some(r#"say "hi", then go"#));
+    }
+
+    #[test]
+    fn link_header_malformed() {
+        assert!(parse_link_header("").is_empty());
+        assert!(parse_link_header("no links here").is_empty());
+        assert_eq!(
+            parse_link_header("</ok>; rel=next, <unterminated"),
+            vec![link("/ok", &[("rel", "next")])]
+        );
+    }
+
+    #[test]
+    fn quoted_strings() {
+        assert_eq!(
+            split_quoted(r#""a \"b\"" rest"#),
+            (r#"a "b""#.to_string(), " rest")
+        );
+        assert_eq!(split_quoted("token"), (String::new(), "token"));
+        assert_eq!(split_quoted(r#""open"#), ("open".to_string(), ""));
+    }
+
+    #[test]
+    fn media_type_params() {
+        let value = r#"multipart/byteranges; charset=utf-8; Boundary="a;b""#;
+        assert_eq!(media_type_param(value, "boundary").as_deref(), Some("a;b"));
+        assert_eq!(media_type_param(value, "charset").as_deref(), Some("utf-8"));
+        assert_eq!(media_type_param(value, "missing"), None);
+        assert_eq!(media_type_param("text/plain", "charset"), None);
+    }
+
+    #[test]
+    fn content_range() {
+        assert_eq!(parse_content_range("bytes 0-499/1234"), Some((0, 499)));
+        assert_eq!(parse_content_range(" bytes 500-999/*"), Some((500, 999)));
+        assert_eq!(parse_content_range("bytes 9-1/10"), None);
+        assert_eq!(parse_content_range("bytes */1234"), None);
+        assert_eq!(parse_content_range("items 0-1/2"), None);
+    }
+
+    #[test]
+    fn http_dates() {
+        let expected = SystemTime::UNIX_EPOCH + Duration::from_secs(784_111_777);
+        assert_eq!(
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(expected)
+        );
+        assert_eq!(
+            parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"),
+            Some(expected)
+        );
+        assert_eq!(parse_http_date("Sun Nov  6 08:49:37 1994"), Some(expected));
+        assert_eq!(
+            parse_http_date("Thu, 01 Jan 1970 00:00:00 GMT"),
+            Some(SystemTime::UNIX_EPOCH)
+        );
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 UTC"), None);
+        assert_eq!(parse_http_date("Sun, 32 Nov 1994 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 24:00:00 GMT"), None);
+        assert_eq!(parse_http_date("yesterday"), None);
+    }
+}
@@ -0,0 +1,510 @@
+//! Hooks that can inspect and rewrite requests and responses of a client.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use http::status::StatusCode;
+
+use crate::http_client::{Error, HttpRequest, HttpResponse, PostTransferInfo, RequestStats};
+
+/// A boxed future, as returned by the callbacks interceptors are built from.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+///
+/// What the client does with a response once an interceptor has seen it.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InterceptorAction {
+    /// Hand the response to the next interceptor, or to the caller.
+    Continue,
+    /// Send the request again, with any changes the interceptor made to it.
+    Retry,
+}
+
+///
+/// Hook run by `HttpClient` around every request, registered through
+/// `HttpClientConfig::interceptors` or `HttpClient::interceptor`.
+///
+/// Interceptors run in registration order. A retry starts over with
+/// `before_request`, and at most `MAX_INTERCEPTOR_RETRIES` retries are made
+/// for one `perform`. Requests with a streaming body are never retried.
+///
+#[async_trait]
+pub trait Interceptor: fmt::Debug + Send + Sync {
+    /// Called by `HttpClient::request` before the request is handed to
+    /// libcurl.
+    fn before_request(&self, _request: &mut HttpRequest) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called by `HttpClient::perform` before each attempt. Returning a
+    /// response skips the transfer, as if the server had sent it; the first
+    /// interceptor answering wins, and `after_response` still runs.
+    fn respond(&self, _request: &HttpRequest) -> Option<HttpResponse> {
+        None
+    }
+
+    /// Called by `HttpClient::perform` once the response has arrived.
+    async fn after_response(
+        &self,
+        _request: &mut HttpRequest,
+        _response: &mut HttpResponse,
+    ) -> Result<InterceptorAction, Error> {
+        Ok(InterceptorAction::Continue)
+    }
+}
+
+#[async_trait]
+impl<T: Interceptor + ?Sized> Interceptor for Arc<T> {
+    fn before_request(&self, request: &mut HttpRequest) -> Result<(), Error> {
+        (**self).before_request(request)
+    }
+
+    fn respond(&self, request: &HttpRequest) -> Option<HttpResponse> {
+        (**self).respond(request)
+    }
+
+    async fn after_response(
+        &self,
+        request: &mut HttpRequest,
+        response: &mut HttpResponse,
+    ) -> Result<InterceptorAction, Error> {
+        (**self).after_response(request, response).await
+    }
+}
+
+///
+/// Interceptors compiled into the type of a client, added with
+/// `HttpClient::with_static_interceptor`. Each one wraps the chain so far in
+/// a `Stack`, so the calls are resolved at compile time instead of going
+/// through `dyn Interceptor`.
+///
+/// The trait is sealed: every `Interceptor` is a `StaticInterceptor`, as are
+/// `()`, the empty chain, and `Stack`. Static interceptors run after the
+/// ones in `HttpClientConfig::interceptors`, in registration order too.
+///
+pub trait StaticInterceptor: sealed::Sealed + fmt::Debug + Send + Sync {
+    fn before_request(&self, request: &mut HttpRequest) -> Result<(), Error>;
+
+    fn respond(&self, request: &HttpRequest) -> Option<HttpResponse>;
+
+    fn after_response<'a>(
+        &'a self,
+        request: &'a mut HttpRequest,
+        response: &'a mut HttpResponse,
+    ) -> impl Future<Output = Result<InterceptorAction, Error>> + Send + 'a;
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for () {}
+    impl<T: super::Interceptor> Sealed for T {}
+    impl<Inner, Outer> Sealed for super::Stack<Inner, Outer> {}
+}
+
+impl StaticInterceptor for () {
+    fn before_request(&self, _request: &mut HttpRequest) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn respond(&self, _request: &HttpRequest) -> Option<HttpResponse> {
+        None
+    }
+
+    fn after_response<'a>(
+        &'a self,
+        _request: &'a mut HttpRequest,
+        _response: &'a mut HttpResponse,
+    ) -> impl Future<Output = Result<InterceptorAction, Error>> + Send + 'a {
+        std::future::ready(Ok(InterceptorAction::Continue))
+    }
+}
+
+impl<T: Interceptor> StaticInterceptor for T {
+    fn before_request(&self, request: &mut HttpRequest) -> Result<(), Error> {
+        Interceptor::before_request(self, request)
+    }
+
+    fn respond(&self, request: &HttpRequest) -> Option<HttpResponse> {
+        Interceptor::respond(self, request)
+    }
+
+    fn after_response<'a>(
+        &'a self,
+        request: &'a mut HttpRequest,
+        response: &'a mut HttpResponse,
+    ) -> impl Future<Output = Result<InterceptorAction, Error>> + Send + 'a {
+        Interceptor::after_response(self, request, response)
+    }
+}
+
+///
+/// A chain of static interceptors: `Inner` runs first, then `Outer`.
+///
+#[derive(Clone, Debug, Default)]
+pub struct Stack<Inner, Outer> {
+    inner: Inner,
+    outer: Outer,
+}
+
+impl<Inner, Outer> Stack<Inner, Outer> {
+    pub fn new(inner: Inner, outer: Outer) -> Self {
+        Self { inner, outer }
+    }
+}
+
+impl<Inner: StaticInterceptor, Outer: StaticInterceptor> StaticInterceptor for Stack<Inner, Outer> {
+    fn before_request(&self, request: &mut HttpRequest) -> Result<(), Error> {
+        self.inner.before_request(request)?;
+        self.outer.before_request(request)
+    }
+
+    fn respond(&self, request: &HttpRequest) -> Option<HttpResponse> {
+        self.inner
+            .respond(request)
+            .or_else(|| self.outer.respond(request))
+    }
+
+    async fn after_response(
+        &self,
+        request: &mut HttpRequest,
+        response: &mut HttpResponse,
+    ) -> Result<InterceptorAction, Error> {
+        if self.inner.after_response(request, response).await? == InterceptorAction::Retry {
+            return Ok(InterceptorAction::Retry);
+        }
+        self.outer.after_response(request, response).await
+    }
+}
+
+/// Upper bound on the retries interceptors can ask for in one `perform`.
+pub const MAX_INTERCEPTOR_RETRIES: u32 = 3;
+
+///
+/// Fetches a fresh API key when a request comes back `401 Unauthorized` and
+/// sends the request again with it.
+///
+/// The key is sent verbatim as the value of `header_name`, so a bearer token
+/// for `Authorization` has to include the `Bearer ` prefix. Once fetched, the
+/// key is also set on every later request passing through the rotator. No
+/// retry is made if `fetch_key` returns the key the request already carried.
+///
+pub struct ApiKeyRotator {
+    pub fetch_key: Box<dyn Fn() -> BoxFuture<'static, Result<String, Error>> + Send + Sync>,
+    pub header_name: HeaderName,
+    current: Mutex<Option<HeaderValue>>,
+}
+
+impl ApiKeyRotator {
+    pub fn new<F>(header_name: HeaderName, fetch_key: F) -> Self
+    where
+        F: Fn() -> BoxFuture<'static, Result<String, Error>> + Send + Sync + 'static,
+    {
+        Self {
+            fetch_key: Box::new(fetch_key),
+            header_name,
+            current: Mutex::new(None),
+        }
+    }
+}
+
+impl fmt::Debug for ApiKeyRotator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ApiKeyRotator")
+            .field("header_name", &self.header_name)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl Interceptor for ApiKeyRotator {
+    fn before_request(&self, request: &mut HttpRequest) -> Result<(), Error> {
+        if let Some(key) = self.current.lock().unwrap().clone() {
+            request.headers.insert(self.header_name.clone(), key);
+        }
+        Ok(())
+    }
+
+    async fn after_response(
+        &self,
+        request: &mut HttpRequest,
+        response: &mut HttpResponse,
+    ) -> Result<InterceptorAction, Error> {
+        if response.status_code != StatusCode::UNAUTHORIZED {
+            return Ok(InterceptorAction::Continue);
+        }
+
+        let key = (self.fetch_key)().await?;
+        let key = HeaderValue::from_str(&key)
+            .map_err(|_| Error::Other(format!("invalid {} header value", self.header_name)))?;
+        if request.headers.get(&self.header_name) == Some(&key) {
+            return Ok(InterceptorAction::Continue);
+        }
+
+        println!("Rotating {} after 401 Unauthorized", self.header_name);
+        *self.current.lock().unwrap() = Some(key.clone());
+        request.headers.insert(self.header_name.clone(), key);
+        Ok(InterceptorAction::Retry)
+    }
+}
+
+///
+/// Logs a warning for every response announcing, through `Deprecation` or
+/// `Sunset` headers, that its resource is deprecated or going away.
+///
+#[derive(Clone, Debug, Default)]
+pub struct DeprecationInterceptor;
+
+#[async_trait]
+impl Interceptor for DeprecationInterceptor {
+    async fn after_response(
+        &self,
+        request: &mut HttpRequest,
+        response: &mut HttpResponse,
+    ) -> Result<InterceptorAction, Error> {
+        if let Some(warning) = response.deprecation_warning() {
+            let sunset = warning
+                .sunset
+                .and_then(|sunset| sunset.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .map(|sunset| format!(", sunset at {} (Unix time)", sunset.as_secs()))
+                .unwrap_or_default();
+            println!(
+                "Warning! {} {} is {}{}",
+                request.method,
+                request.url,
+                if warning.deprecated {
+                    "deprecated"
+                } else {
+                    "scheduled for deprecation"
+                },
+                sunset
+            );
+        }
+        Ok(InterceptorAction::Continue)
+    }
+}
+
+///
+/// Keeps the total times of the last `window_size` transfers and reports
+/// their distribution. Register it through an `Arc` to read the figures
+/// while the client is in use.
+///
+#[derive(Debug)]
+pub struct LatencySampler {
+    window_size: usize,
+    samples: parking_lot::Mutex<VecDeque<Duration>>,
+}
+
+impl LatencySampler {
+    pub fn new(window_size: usize) -> Self {
+        Self {
+            window_size,
+            samples: parking_lot::Mutex::new(VecDeque::with_capacity(window_size)),
+        }
+    }
+
+    /// Adds a sample, dropping the oldest one once the window is full.
+    pub fn record(&self, latency: Duration) {
+        if self.window_size == 0 {
+            return;
+        }
+        let mut samples = self.samples.lock();
+        if samples.len() == self.window_size {
+            samples.pop_front();
+        }
+        samples.push_back(latency);
+    }
+
+    pub fn p50(&self) -> Duration {
+        self.percentile(50)
+    }
+
+    pub fn p95(&self) -> Duration {
+        self.percentile(95)
+    }
+
+    pub fn p99(&self) -> Duration {
+        self.percentile(99)
+    }
+
+    /// Zero while no sample has been recorded.
+    pub fn mean(&self) -> Duration {
+        let samples = self.samples.lock();
+        if samples.is_empty() {
+            return Duration::ZERO;
+        }
+        samples.iter().sum::<Duration>() / samples.len() as u32
+    }
+
+    /// Nearest-rank percentile of the window, zero while it is empty.
+    fn percentile(&self, percent: usize) -> Duration {
+        let mut sorted = self.samples.lock().iter().copied().collect::<Vec<_>>();
+        if sorted.is_empty() {
+            return Duration::ZERO;
+        }
+        sorted.sort_unstable();
+        let rank = (percent * sorted.len()).div_ceil(100);
+        sorted[rank.max(1) - 1]
+    }
+}
+
+#[async_trait]
+impl Interceptor for LatencySampler {
+    async fn after_response(
+        &self,
+        _request: &mut HttpRequest,
+        response: &mut HttpResponse,
+    ) -> Result<InterceptorAction, Error> {
+        self.record(response.info.timings.total);
+        Ok(InterceptorAction::Continue)
+    }
+}
+
+///
+/// Answers every request with an empty-headed `200 OK` whose body is
+/// `dry-run` instead of sending it, and keeps the requests as they would
+/// have been sent, after every `before_request`. Register it through an `Arc`
+/// to inspect them, e.g. to check a production configuration offline.
+///
+#[derive(Debug, Default)]
+pub struct DryRunInterceptor {
+    requests: Mutex<Vec<HttpRequest>>,
+}
+
+impl DryRunInterceptor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The requests seen so far, in order.
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Interceptor for DryRunInterceptor {
+    fn respond(&self, request: &HttpRequest) -> Option<HttpResponse> {
+        println!("Dry run, not sending {} {}", request.method, request.url);
+        self.requests.lock().unwrap().push(request.clone());
+        Some(HttpResponse {
+            status_code: StatusCode::OK,
+            headers: HeaderMap::new(),
+            trailers: HeaderMap::new(),
+            body: b"dry-run".to_vec(),
+            stats: RequestStats::default(),
+            set_cookies: Vec::new(),
+            info: PostTransferInfo {
+                effective_url: Some(request.url.clone()),
+                ..Default::default()
+            },
+            last_modified_time: None,
+            elapsed: Duration::ZERO,
+            correlation_id: request.correlation_id.clone(),
+        })
+    }
+}
+
+///
+/// Fails responses whose header section, counted as `name: value` lines
+/// with their CRLF, exceeds `max_bytes`, with `Error::ResponseTooLarge`.
+///
+/// Interceptors see a response once it has arrived, so the headers and the
+/// body have been read by then. To stop the transfer as soon as the header
+/// section is too large, set `HttpClientConfig::max_response_header_size`
+/// instead; this interceptor is for handlers without a `TransferState`.
+///
+#[derive(Clone, Debug)]
+pub struct MaxHeaderSizeInterceptor {
+    pub max_bytes: u64,
+}
+
+impl MaxHeaderSizeInterceptor {
+    pub fn new(max_bytes: u64) -> Self {
+        Self { max_bytes }
+    }
+}
+
+#[async_trait]
+impl Interceptor for MaxHeaderSizeInterceptor {
+    async fn after_response(
+        &self,
+        _request: &mut HttpRequest,
+        response: &mut HttpResponse,
+    ) -> Result<InterceptorAction, Error> {
+        let size = response
+            .headers
+            .iter()
+            .map(|(name, value)| (name.as_str().len() + value.len() + 4) as u64)
+            .sum::<u64>();
+        if size > self.max_bytes {
+            return Err(Error::ResponseTooLarge {
+                limit: self.max_bytes,
+                actual: size,
+            });
+        }
+        Ok(InterceptorAction::Continue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(values: impl IntoIterator<Item = u64>) -> LatencySampler {
+        let sampler = LatencySampler::new(100);
+        for value in values {
+            sampler.record(Duration::from_millis(value));
+        }
+        sampler
+    }
+
+    #[test]
+    fn empty_window() {
+        let sampler = LatencySampler::new(10);
+        assert_eq!(sampler.p50(), Duration::ZERO);
+        assert_eq!(sampler.p99(), Duration::ZERO);
+        assert_eq!(sampler.mean(), Duration::ZERO);
+    }
+
+    #[test]
+    fn nearest_rank_percentiles() {
+        // Recorded out of order, 1ms to 100ms.
+        let sampler = millis((1..=100).rev());
+        assert_eq!(sampler.p50(), Duration::from_millis(50));
+        assert_eq!(sampler.p95(), Duration::from_millis(95));
+        assert_eq!(sampler.p99(), Duration::from_millis(99));
+        assert_eq!(sampler.percentile(100), Duration::from_millis(100));
+        assert_eq!(sampler.percentile(0), Duration::from_millis(1));
+
+        let sampler = millis([30, 10, 20]);
+        assert_eq!(sampler.p50(), Duration::from_millis(20));
+        assert_eq!(sampler.p95(), Duration::from_millis(30));
+        assert_eq!(sampler.mean(), Duration::from_millis(20));
+
+        let sampler = millis([7]);
+        assert_eq!(sampler.p50(), Duration::from_millis(7));
+        assert_eq!(sampler.p99(), Duration::from_millis(7));
+    }
+
+    #[test]
+    fn window_drops_oldest() {
+        let sampler = LatencySampler::new(3);
+        for value in [100, 1, 2, 3] {
+            sampler.record(Duration::from_millis(value));
+        }
+        assert_eq!(sampler.p99(), Duration::from_millis(3));
+        assert_eq!(sampler.mean(), Duration::from_millis(2));
+
+        let sampler = LatencySampler::new(0);
+        sampler.record(Duration::from_millis(5));
+        assert_eq!(sampler.p50(), Duration::ZERO);
+    }
+}
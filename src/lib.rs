@@ -0,0 +1,13 @@
+mod checksum;
+mod compression;
+pub mod cookie;
+mod curl_opt;
+mod encoding;
+pub mod ftp;
+pub mod headers;
+pub mod http_client;
+pub mod interceptor;
+pub mod smtp;
+pub mod throttle;
+#[cfg(feature = "webdav")]
+pub mod webdav;
@@ -0,0 +1,161 @@
+//! Client-side rate limiting of requests.
+
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use tokio::sync::Semaphore;
+use tokio::time::MissedTickBehavior;
+
+use crate::http_client::{Error, ExtendedHandler, HttpClient, HttpResponse, Perform};
+use crate::interceptor::StaticInterceptor;
+
+///
+/// Token bucket limiting the requests performed through it to `rate` per
+/// `interval`, with up to `burst` requests allowed at once after a quiet
+/// period. Requests over the limit wait for a token instead of failing.
+///
+/// Clients are single-use, so the bucket is shared by handing every
+/// configured client to `ThrottledClient::perform`. Clone it, or keep it in
+/// an `Arc`, to share one limit between tasks.
+///
+#[derive(Clone, Debug)]
+pub struct ThrottledClient {
+    rate: u32,
+    interval: Duration,
+    burst: u32,
+    semaphore: Arc<Semaphore>,
+}
+
+impl ThrottledClient {
+    /// Starts with a full bucket of `burst` tokens, raised to at least
+    /// `rate`, and adds `rate` tokens every `interval`. Must be called from
+    /// within a Tokio runtime, which runs the refill task. Panics if `rate`
+    /// or `interval` is zero, as the bucket would never be refilled.
+    pub fn new(rate: u32, interval: Duration, burst: u32) -> Self {
+        assert!(rate > 0, "rate limit must allow at least one request");
+        assert!(!interval.is_zero(), "rate limit interval must not be zero");
+        let burst = burst.max(rate);
+        let semaphore = Arc::new(Semaphore::new(burst as usize));
+        tokio::spawn(refill(
+            Arc::downgrade(&semaphore),
+            rate as usize,
+            interval,
+            burst as usize,
+        ));
+        Self {
+            rate,
+            interval,
+            burst,
+            semaphore,
+        }
+    }
+
+    pub fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+
+    /// Waits for a token, then performs the request.
+    pub async fn perform<H: ExtendedHandler, C: StaticInterceptor>(
+        &self,
+        client: HttpClient<H, Perform, C>,
+    ) -> Result<HttpResponse, Error> {
+        self.semaphore
+            .acquire()
+            .await
+            .map_err(|e| Error::Other(format!("rate limiter closed: {}", e)))?
+            // The token is used up, only `refill` hands out new ones.
+            .forget();
+        client.perform().await
+    }
+}
+
+/// Tops the bucket up on every tick until the last `ThrottledClient` is gone.
+async fn refill(semaphore: Weak<Semaphore>, rate: usize, interval: Duration, burst: usize) {
+    let mut ticks = tokio::time::interval(interval);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    // The first tick completes immediately, and the bucket starts full.
+    ticks.tick().await;
+    loop {
+        ticks.tick().await;
+        let Some(semaphore) = semaphore.upgrade() else {
+            break;
+        };
+        let missing = burst.saturating_sub(semaphore.available_permits());
+        semaphore.add_permits(rate.min(missing));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use curl::easy::Easy2;
+    use tokio::time::Instant;
+    use url::Url;
+
+    use super::*;
+    use crate::http_client::{HttpRequestBuilder, NullHandler};
+    use crate::interceptor::DryRunInterceptor;
+
+    /// Performs `count` requests one after the other, answered without a
+    /// transfer, and returns when each completed, relative to the start.
+    async fn perform_all(throttle: &ThrottledClient, count: usize) -> Vec<Duration> {
+        let start = Instant::now();
+        let url = Url::parse("http://localhost/").unwrap();
+        let mut completed = Vec::new();
+        for _ in 0..count {
+            let client = HttpClient::new(Easy2::new(NullHandler::new()))
+                .interceptor(DryRunInterceptor::default())
+                .request(HttpRequestBuilder::new(url.clone()).build())
+                .unwrap();
+            throttle.perform(client).await.unwrap();
+            completed.push(start.elapsed());
+        }
+        completed
+    }
+
+    fn millis(values: &[u64]) -> Vec<Duration> {
+        values.iter().copied().map(Duration::from_millis).collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_per_interval() {
+        let throttle = ThrottledClient::new(2, Duration::from_secs(1), 0);
+        assert_eq!(throttle.burst(), 2);
+        assert_eq!(
+            perform_all(&throttle, 6).await,
+            millis(&[0, 0, 1000, 1000, 2000, 2000])
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn burst_after_quiet_period() {
+        let throttle = ThrottledClient::new(1, Duration::from_secs(1), 3);
+        assert_eq!(perform_all(&throttle, 4).await, millis(&[0, 0, 0, 1000]));
+
+        // Nine ticks refill the bucket, but not beyond `burst`. Half an
+        // interval off the ticks, the next one comes after 500ms.
+        tokio::time::sleep(Duration::from_millis(9_500)).await;
+        assert_eq!(perform_all(&throttle, 4).await, millis(&[0, 0, 0, 500]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shared_between_clones() {
+        let throttle = ThrottledClient::new(1, Duration::from_secs(1), 1);
+        let other = throttle.clone();
+        assert_eq!(perform_all(&throttle, 2).await, millis(&[0, 1000]));
+        assert_eq!(perform_all(&other, 1).await, millis(&[1000]));
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "at least one request")]
+    async fn zero_rate() {
+        ThrottledClient::new(0, Duration::from_secs(1), 5);
+    }
+}
@@ -0,0 +1,350 @@
+//! WebDAV `207 Multi-Status` responses (RFC 4918 §13).
+
+use std::collections::HashMap;
+
+use http::StatusCode;
+
+use crate::http_client::{Error, HttpResponse};
+
+///
+/// Status and properties of one resource in a multi-status response.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DavResponse {
+    pub href: String,
+    /// The `<status>` of the response, or of its first `<propstat>` if the
+    /// response has none.
+    pub status: StatusCode,
+    /// Text content of the properties reported with a 2xx `<propstat>`,
+    /// keyed by local name (`getcontentlength`, `displayname`, ...).
+    /// Properties holding only elements, such as `resourcetype`, list the
+    /// local names of those elements separated by spaces.
+    pub properties: HashMap<String, String>,
+}
+
+impl HttpResponse {
+    /// Parses the XML body of a `207 Multi-Status` response.
+    pub fn multi_status(&self) -> Result<Vec<DavResponse>, Error> {
+        if self.status_code != StatusCode::MULTI_STATUS {
+            return Err(Error::Other(format!(
+                "expected 207 Multi-Status, got {}",
+                self.status_code
+            )));
+        }
+        let body = std::str::from_utf8(&self.body)
+            .map_err(|_| Error::Other("multi-status body is not UTF-8".to_string()))?;
+        let root = parse_xml(body)?;
+        if root.name != "multistatus" {
+            return Err(Error::Other(format!(
+                "expected <multistatus> root element, got <{}>",
+                root.name
+            )));
+        }
+        root.children("response").map(dav_response).collect()
+    }
+}
+
+fn dav_response(response: &Element) -> Result<DavResponse, Error> {
+    let href = response
+        .child("href")
+        .map(|href| href.text.trim().to_string())
+        .ok_or_else(|| Error::Other("multi-status response without <href>".to_string()))?;
+
+    let mut status = response
+        .child("status")
+        .map(|status| parse_status(&status.text));
+    let mut properties = HashMap::new();
+    for propstat in response.children("propstat") {
+        let propstat_status = propstat
+            .child("status")
+            .map(|status| parse_status(&status.text))
+            .transpose()?;
+        if status.is_none() {
+            status = propstat_status.map(Ok);
+        }
+        if !propstat_status.is_some_and(|status| status.is_success()) {
+            continue;
+        }
+        for prop in propstat
+            .children("prop")
+            .flat_map(|prop| prop.elements.iter())
+        {
+            let value = if prop.text.trim().is_empty() && !prop.elements.is_empty() {
+                prop.elements
+                    .iter()
+                    .map(|element| element.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            } else {
+                prop.text.trim().to_string()
+            };
+            properties.insert(prop.name.clone(), value);
+        }
+    }
+
+    Ok(DavResponse {
+        status: status
+            .transpose()?
+            .ok_or_else(|| Error::Other(format!("no status for {}", href)))?,
+        href,
+        properties,
+    })
+}
+
+/// Parses a status line such as `HTTP/1.1 404 Not Found`.
+fn parse_status(line: &str) -> Result<StatusCode, Error> {
+    line.split_ascii_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .and_then(|code| StatusCode::from_u16(code).ok())
+        .ok_or_else(|| Error::Other(format!("invalid status line {:?}", line.trim())))
+}
+
+/// Element of the minimal XML tree built from the body. Names are local
+/// names, namespace prefixes are dropped.
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    text: String,
+    elements: Vec<Element>,
+}
+
+impl Element {
+    fn child<'a>(&'a self, name: &'a str) -> Option<&'a Element> {
+        self.children(name).next()
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.elements
+            .iter()
+            .filter(move |element| element.name == name)
+    }
+}
+
+fn parse_xml(input: &str) -> Result<Element, Error> {
+    let malformed = |what: &str| Error::Other(format!("malformed multi-status XML: {}", what));
+    let mut stack: Vec<Element> = vec![Element::default()];
+    let mut rest = input;
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            append_text(&mut stack, rest)?;
+            break;
+        };
+        append_text(&mut stack, &rest[..start])?;
+        rest = &rest[start..];
+
+        if let Some(after) = rest.strip_prefix("<?") {
+            rest = &after[after
+                .find("?>")
+                .ok_or_else(|| malformed("unterminated <?"))?
+                + 2..];
+        } else if let Some(after) = rest.strip_prefix("<!--") {
+            rest = &after[after
+                .find("-->")
+                .ok_or_else(|| malformed("unterminated comment"))?
+                + 3..];
+        } else if let Some(after) = rest.strip_prefix("<![CDATA[") {
+            let end = after
+                .find("]]>")
+                .ok_or_else(|| malformed("unterminated CDATA"))?;
+            if let Some(element) = stack.last_mut() {
+                element.text.push_str(&after[..end]);
+            }
+            rest = &after[end + 3..];
+        } else if let Some(after) = rest.strip_prefix("<!") {
+            rest = &after[after
+                .find('>')
+                .ok_or_else(|| malformed("unterminated <!"))?
+                + 1..];
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let end = after
+                .find('>')
+                .ok_or_else(|| malformed("unterminated end tag"))?;
+            let name = local_name(after[..end].trim());
+            let element = stack.pop().filter(|element| element.name == name);
+            let (Some(element), Some(parent)) = (element, stack.last_mut()) else {
+                return Err(malformed(&format!("unexpected </{}>", name)));
+            };
+            parent.elements.push(element);
+            rest = &after[end + 1..];
+        } else {
+            let end = rest
+                .find('>')
+                .ok_or_else(|| malformed("unterminated tag"))?;
+            let tag = &rest[1..end];
+            let (tag, self_closing) = match tag.strip_suffix('/') {
+                Some(tag) => (tag, true),
+                None => (tag, false),
+            };
+            let name = tag
+                .split(|c: char| c.is_ascii_whitespace())
+                .next()
+                .unwrap_or_default();
+            let element = Element {
+                name: local_name(name).to_string(),
+                ..Default::default()
+            };
+            if self_closing {
+                stack
+                    .last_mut()
+                    .ok_or_else(|| malformed("element outside document"))?
+                    .elements
+                    .push(element);
+            } else {
+                stack.push(element);
+            }
+            rest = &rest[end + 1..];
+        }
+    }
+
+    match (stack.pop(), stack.is_empty()) {
+        (Some(mut document), true) if document.elements.len() == 1 => {
+            Ok(document.elements.remove(0))
+        }
+        _ => Err(malformed("expected a single root element")),
+    }
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+fn append_text(stack: &mut [Element], text: &str) -> Result<(), Error> {
+    if let Some(element) = stack.last_mut() {
+        element.text.push_str(&decode_entities(text)?);
+    }
+    Ok(())
+}
+
+fn decode_entities(text: &str) -> Result<String, Error> {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp + 1..];
+        let end = rest
+            .find(';')
+            .ok_or_else(|| Error::Other("unterminated XML entity".to_string()))?;
+        let entity = &rest[..end];
+        let c = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        decoded.push(c.ok_or_else(|| Error::Other(format!("unknown XML entity &{};", entity)))?);
+        rest = &rest[end + 1..];
+    }
+    decoded.push_str(rest);
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_client::tests::test_response;
+
+    const PROPFIND: &str = r#"<?xml version="1.0" encoding="utf-8" ?>
+<D:multistatus xmlns:D="DAV:">
+  <!-- a collection and a file -->
+  <D:response>
+    <D:href>/docs/</D:href>
+    <D:propstat>
+      <D:prop>
+        <D:displayname>Docs &amp; notes</D:displayname>
+        <D:resourcetype><D:collection/></D:resourcetype>
+      </D:prop>
+      <D:status>HTTP/1.1 200 OK</D:status>
+    </D:propstat>
+  </D:response>
+  <D:response>
+    <D:href>/docs/a.txt</D:href>
+    <D:propstat>
+      <D:prop>
+        <D:getcontentlength>42</D:getcontentlength>
+        <D:resourcetype/>
+        <D:displayname><![CDATA[a <draft>]]></D:displayname>
+      </D:prop>
+      <D:status>HTTP/1.1 200 OK</D:status>
+    </D:propstat>
+    <D:propstat>
+      <D:prop><D:getetag/></D:prop>
+      <D:status>HTTP/1.1 404 Not Found</D:status>
+    </D:propstat>
+  </D:response>
+  <D:response>
+    <D:href>/docs/locked.txt</D:href>
+    <D:status>HTTP/1.1 423 Locked</D:status>
+  </D:response>
+</D:multistatus>"#;
+
+    #[test]
+    fn propfind_response() {
+        let responses = test_response(207, &[], PROPFIND.as_bytes())
+            .multi_status()
+            .unwrap();
+        assert_eq!(responses.len(), 3);
+
+        assert_eq!(responses[0].href, "/docs/");
+        assert_eq!(responses[0].status, StatusCode::OK);
+        assert_eq!(responses[0].properties["displayname"], "Docs & notes");
+        assert_eq!(responses[0].properties["resourcetype"], "collection");
+
+        assert_eq!(responses[1].href, "/docs/a.txt");
+        assert_eq!(responses[1].properties["getcontentlength"], "42");
+        assert_eq!(responses[1].properties["resourcetype"], "");
+        assert_eq!(responses[1].properties["displayname"], "a <draft>");
+        // Only properties of a 2xx propstat are kept.
+        assert!(!responses[1].properties.contains_key("getetag"));
+
+        assert_eq!(responses[2].status, StatusCode::LOCKED);
+        assert!(responses[2].properties.is_empty());
+    }
+
+    #[test]
+    fn not_multi_status() {
+        assert!(test_response(200, &[], PROPFIND.as_bytes())
+            .multi_status()
+            .is_err());
+        let body = "<D:error xmlns:D=\"DAV:\"/>";
+        assert!(test_response(207, &[], body.as_bytes())
+            .multi_status()
+            .is_err());
+    }
+
+    #[test]
+    fn malformed_xml() {
+        for body in [
+            "<multistatus><response></multistatus>",
+            "<multistatus>",
+            "<multistatus/><multistatus/>",
+            "<multistatus><response><href>&bogus;</href></response></multistatus>",
+            "<multistatus><response><href>/</href></response></multistatus>",
+            "<multistatus><response><href>/</href><status>nope</status></response></multistatus>",
+        ] {
+            assert!(
+                test_response(207, &[], body.as_bytes())
+                    .multi_status()
+                    .is_err(),
+                "{}",
+                body
+            );
+        }
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(
+            decode_entities("&lt;a&gt; &quot;&apos; &#65;&#x42;").unwrap(),
+            "<a> \"' AB"
+        );
+        assert!(decode_entities("&amp").is_err());
+    }
+}
@@ -0,0 +1,5 @@
+//! Tests of `HttpClient` against a local mock server.
+
+mod methods;
+mod mock;
+mod retries;
@@ -0,0 +1,194 @@
+//! Requests of every method and the transfer failures callers see.
+
+use std::time::Duration;
+
+use curl::easy::Easy2;
+use http::header::{CONTENT_TYPE, LOCATION};
+use http::{Method, StatusCode};
+use http_client_example::http_client::{
+    Build, Error, HttpClient, HttpRequestBuilder, InMemoryHandler,
+};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+
+use crate::mock::{MockServer, Reply};
+
+fn client() -> HttpClient<InMemoryHandler, Build> {
+    HttpClient::new(Easy2::new(InMemoryHandler::new()))
+}
+
+#[tokio::test]
+async fn get_json() {
+    let server = MockServer::start(|_, _| {
+        Reply::status(200, &[("Content-Type", "application/json")], br#"{"id":1}"#)
+    })
+    .await;
+
+    let response = client()
+        .exchange(HttpRequestBuilder::new(server.url("/items/1")).build())
+        .await
+        .unwrap();
+    assert_eq!(response.status_code, StatusCode::OK);
+    assert_eq!(response.headers[CONTENT_TYPE], "application/json");
+    assert_eq!(response.body, br#"{"id":1}"#);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, "GET");
+    assert_eq!(requests[0].path, "/items/1");
+}
+
+#[tokio::test]
+async fn post_form() {
+    let server = MockServer::start(|_, _| Reply::status(201, &[], b"")).await;
+
+    let response = client()
+        .exchange(
+            HttpRequestBuilder::new(server.url("/items"))
+                .method(Method::POST)
+                .header(
+                    CONTENT_TYPE,
+                    "application/x-www-form-urlencoded".parse().unwrap(),
+                )
+                .body(b"name=widget&count=2".to_vec())
+                .build(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status_code, StatusCode::CREATED);
+
+    let request = &server.requests()[0];
+    assert_eq!(request.method, "POST");
+    assert_eq!(
+        request.header("content-type"),
+        Some("application/x-www-form-urlencoded")
+    );
+    assert_eq!(request.body, b"name=widget&count=2");
+}
+
+#[tokio::test]
+async fn put_file() {
+    let server = MockServer::start(|_, _| Reply::status(204, &[], b"")).await;
+    let path = std::env::temp_dir().join(format!("put-file-{}.json", std::process::id()));
+    std::fs::write(&path, br#"{"name":"widget"}"#).unwrap();
+
+    let request = HttpRequestBuilder::new(server.url("/items/1"))
+        .method(Method::PUT)
+        .build()
+        .with_file_body(&path)
+        .unwrap();
+    let response = client().exchange(request).await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(response.status_code, StatusCode::NO_CONTENT);
+
+    let request = &server.requests()[0];
+    assert_eq!(request.method, "PUT");
+    assert_eq!(request.header("content-type"), Some("application/json"));
+    assert_eq!(request.body, br#"{"name":"widget"}"#);
+}
+
+#[tokio::test]
+async fn delete_no_content() {
+    let server = MockServer::start(|_, _| Reply::status(204, &[], b"")).await;
+
+    let response = client()
+        .exchange(
+            HttpRequestBuilder::new(server.url("/items/1"))
+                .method(Method::DELETE)
+                .build(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status_code, StatusCode::NO_CONTENT);
+    assert!(response.body.is_empty());
+    assert_eq!(server.requests()[0].method, "DELETE");
+}
+
+#[tokio::test]
+async fn head_headers_only() {
+    // The length of the body a GET would have returned, without the body.
+    let server = MockServer::start(|_, _| {
+        Reply::Raw(b"HTTP/1.1 200 OK\r\nContent-Length: 1234\r\nETag: \"v1\"\r\n\r\n".to_vec())
+    })
+    .await;
+
+    let response = client()
+        .exchange(
+            HttpRequestBuilder::new(server.url("/items/1"))
+                .method(Method::HEAD)
+                .build(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status_code, StatusCode::OK);
+    assert_eq!(response.headers["etag"], "\"v1\"");
+    assert_eq!(response.headers["content-length"], "1234");
+    assert!(response.body.is_empty());
+    assert_eq!(server.requests()[0].method, "HEAD");
+}
+
+#[tokio::test]
+async fn redirect_followed() {
+    let server = MockServer::start(|_, request| match request.path.as_str() {
+        "/old" => Reply::status(301, &[("Location", "/new")], b""),
+        _ => Reply::ok(b"moved here"),
+    })
+    .await;
+
+    let mut easy = Easy2::new(InMemoryHandler::new());
+    easy.follow_location(true).unwrap();
+    let response = HttpClient::new(easy)
+        .exchange(HttpRequestBuilder::new(server.url("/old")).build())
+        .await
+        .unwrap();
+    assert_eq!(response.status_code, StatusCode::OK);
+    assert_eq!(response.body, b"moved here");
+    assert_eq!(response.stats.redirects_followed, 1);
+    assert_eq!(response.info.effective_url, Some(server.url("/new")));
+    assert!(!response.headers.contains_key(LOCATION));
+
+    let paths = server
+        .requests()
+        .into_iter()
+        .map(|request| request.path)
+        .collect::<Vec<_>>();
+    assert_eq!(paths, ["/old", "/new"]);
+}
+
+#[tokio::test]
+async fn timeout() {
+    let server = MockServer::start(|_, _| Reply::Hang).await;
+
+    let result = client()
+        .timeout(Duration::from_millis(200))
+        .unwrap()
+        .exchange(HttpRequestBuilder::new(server.url("/slow")).build())
+        .await;
+    match result {
+        Err(Error::Curl(e)) => assert!(e.is_operation_timedout(), "{:?}", e),
+        other => panic!("expected a timeout, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn tls_error() {
+    // Answers the TLS handshake in plain text, as an HTTP-only port would.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let _ = stream
+                .write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n")
+                .await;
+        }
+    });
+
+    let url = format!("https://{}/", address).parse().unwrap();
+    let result = client()
+        .exchange(HttpRequestBuilder::new(url).build())
+        .await;
+    match result {
+        Err(Error::Curl(e)) => assert!(e.is_ssl_connect_error(), "{:?}", e),
+        other => panic!("expected a TLS error, got {:?}", other),
+    }
+}
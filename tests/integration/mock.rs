@@ -0,0 +1,199 @@
+//! A scripted HTTP/1.1 server on a local port, so that the tests do not
+//! need the network.
+
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use url::Url;
+
+/// A request as the server received it.
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// What the server does with a request.
+pub enum Reply {
+    /// Writes these bytes, a complete response, and keeps the connection.
+    Raw(Vec<u8>),
+    /// Resets the connection without answering.
+    Reset,
+    /// Never answers.
+    Hang,
+}
+
+impl Reply {
+    /// A response with `Content-Length` set from `body`.
+    pub fn status(status: u16, headers: &[(&str, &str)], body: &[u8]) -> Self {
+        let mut response = format!("HTTP/1.1 {} Mock\r\n", status);
+        for (name, value) in headers {
+            response.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        response.push_str(&format!("Content-Length: {}\r\n\r\n", body.len()));
+        let mut response = response.into_bytes();
+        response.extend_from_slice(body);
+        Reply::Raw(response)
+    }
+
+    pub fn ok(body: &[u8]) -> Self {
+        Self::status(200, &[], body)
+    }
+}
+
+pub struct MockServer {
+    address: SocketAddr,
+    requests: Arc<Mutex<Vec<Request>>>,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Starts a server answering every request with `respond`, which gets
+    /// the number of requests received before this one.
+    pub async fn start<F>(respond: F) -> Self
+    where
+        F: Fn(usize, &Request) -> Reply + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let respond = Arc::new(respond);
+        let received = Arc::new(AtomicUsize::new(0));
+        let task = tokio::spawn({
+            let requests = requests.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(serve(
+                        stream,
+                        requests.clone(),
+                        respond.clone(),
+                        received.clone(),
+                    ));
+                }
+            }
+        });
+        Self {
+            address,
+            requests,
+            task,
+        }
+    }
+
+    pub fn url(&self, path: &str) -> Url {
+        Url::parse(&format!("http://{}{}", self.address, path)).unwrap()
+    }
+
+    /// The requests received so far, in order.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve<F>(
+    stream: TcpStream,
+    requests: Arc<Mutex<Vec<Request>>>,
+    respond: Arc<F>,
+    received: Arc<AtomicUsize>,
+) where
+    F: Fn(usize, &Request) -> Reply + Send + Sync + 'static,
+{
+    let mut stream = BufReader::new(stream);
+    while let Some(request) = read_request(&mut stream).await {
+        let reply = respond(received.fetch_add(1, Ordering::SeqCst), &request);
+        requests.lock().unwrap().push(request);
+        match reply {
+            Reply::Raw(response) => {
+                if stream.get_mut().write_all(&response).await.is_err() {
+                    return;
+                }
+            }
+            Reply::Reset => {
+                // A zero linger time makes closing send RST instead of FIN.
+                let _ = stream.get_ref().set_linger(Some(Duration::ZERO));
+                return;
+            }
+            Reply::Hang => std::future::pending().await,
+        }
+    }
+}
+
+/// Reads one request, `None` once the client has closed the connection.
+async fn read_request(stream: &mut BufReader<TcpStream>) -> Option<Request> {
+    let mut line = String::new();
+    stream.read_line(&mut line).await.ok().filter(|n| *n > 0)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        stream.read_line(&mut line).await.ok().filter(|n| *n > 0)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line.split_once(':')?;
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+    let mut request = Request {
+        method,
+        path,
+        headers,
+        body: Vec::new(),
+    };
+
+    if request
+        .header("expect")
+        .is_some_and(|value| value.eq_ignore_ascii_case("100-continue"))
+    {
+        stream
+            .get_mut()
+            .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+            .await
+            .ok()?;
+    }
+    if request
+        .header("transfer-encoding")
+        .is_some_and(|value| value.eq_ignore_ascii_case("chunked"))
+    {
+        loop {
+            line.clear();
+            stream.read_line(&mut line).await.ok()?;
+            let size = usize::from_str_radix(line.trim(), 16).ok()?;
+            let mut chunk = vec![0; size + 2];
+            stream.read_exact(&mut chunk).await.ok()?;
+            if size == 0 {
+                break;
+            }
+            request.body.extend_from_slice(&chunk[..size]);
+        }
+    } else if let Some(length) = request.header("content-length") {
+        let mut body = vec![0; length.parse().ok()?];
+        stream.read_exact(&mut body).await.ok()?;
+        request.body = body;
+    }
+    Some(request)
+}
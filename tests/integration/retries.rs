@@ -0,0 +1,248 @@
+//! The retries `perform` makes on its own or for interceptors, and the
+//! limits it enforces on responses.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use curl::easy::Easy2;
+use http::header::AUTHORIZATION;
+use http::StatusCode;
+use http_client_example::http_client::{
+    DownloadHandler, Error, HttpClient, HttpRequest, HttpRequestBuilder, HttpResponse,
+    InMemoryHandler,
+};
+use http_client_example::interceptor::{
+    ApiKeyRotator, Interceptor, InterceptorAction, MAX_INTERCEPTOR_RETRIES,
+};
+
+use crate::mock::{MockServer, Reply};
+
+/// Waits as long as `Retry-After` asks and sends a `429` request again.
+#[derive(Debug)]
+struct RetryAfter;
+
+#[async_trait]
+impl Interceptor for RetryAfter {
+    async fn after_response(
+        &self,
+        _request: &mut HttpRequest,
+        response: &mut HttpResponse,
+    ) -> Result<InterceptorAction, Error> {
+        if response.status_code != StatusCode::TOO_MANY_REQUESTS {
+            return Ok(InterceptorAction::Continue);
+        }
+        let seconds = response
+            .headers
+            .get("retry-after")
+            .and_then(|value| value.to_str().ok()?.parse().ok())
+            .unwrap_or(1);
+        tokio::time::sleep(Duration::from_secs(seconds)).await;
+        Ok(InterceptorAction::Retry)
+    }
+}
+
+/// Asks for a retry of every response.
+#[derive(Debug)]
+struct AlwaysRetry;
+
+#[async_trait]
+impl Interceptor for AlwaysRetry {
+    async fn after_response(
+        &self,
+        _request: &mut HttpRequest,
+        _response: &mut HttpResponse,
+    ) -> Result<InterceptorAction, Error> {
+        Ok(InterceptorAction::Retry)
+    }
+}
+
+#[tokio::test]
+async fn unauthorized_retried_with_new_credentials() {
+    let server = MockServer::start(|_, request| match request.header("authorization") {
+        Some("Bearer fresh") => Reply::ok(b"welcome"),
+        _ => Reply::status(401, &[], b""),
+    })
+    .await;
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let rotator = ApiKeyRotator::new(AUTHORIZATION, {
+        let fetches = fetches.clone();
+        move || {
+            fetches.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async { Ok("Bearer fresh".to_string()) })
+        }
+    });
+
+    let response = HttpClient::new(Easy2::new(InMemoryHandler::new()))
+        .interceptor(rotator)
+        .exchange(
+            HttpRequestBuilder::new(server.url("/me"))
+                .header(AUTHORIZATION, "Bearer expired".parse().unwrap())
+                .build(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status_code, StatusCode::OK);
+    assert_eq!(response.body, b"welcome");
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+    let sent = server
+        .requests()
+        .into_iter()
+        .map(|request| request.header("authorization").map(str::to_string))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        sent,
+        [
+            Some("Bearer expired".to_string()),
+            Some("Bearer fresh".to_string())
+        ]
+    );
+}
+
+#[tokio::test]
+async fn too_many_requests_retried_after_delay() {
+    let server = MockServer::start(|count, _| match count {
+        0 => Reply::status(429, &[("Retry-After", "1")], b""),
+        _ => Reply::ok(b"done"),
+    })
+    .await;
+
+    let started = Instant::now();
+    let response = HttpClient::new(Easy2::new(InMemoryHandler::new()))
+        .interceptor(RetryAfter)
+        .exchange(HttpRequestBuilder::new(server.url("/jobs")).build())
+        .await
+        .unwrap();
+    assert_eq!(response.status_code, StatusCode::OK);
+    assert_eq!(response.body, b"done");
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn interceptor_retries_are_limited() {
+    let server = MockServer::start(|count, _| Reply::ok(count.to_string().as_bytes())).await;
+
+    let response = HttpClient::new(Easy2::new(InMemoryHandler::new()))
+        .interceptor(AlwaysRetry)
+        .exchange(HttpRequestBuilder::new(server.url("/")).build())
+        .await
+        .unwrap();
+    // The handler was reset before each retry, only the last body is left.
+    assert_eq!(
+        response.body,
+        MAX_INTERCEPTOR_RETRIES.to_string().as_bytes()
+    );
+    assert_eq!(
+        server.requests().len(),
+        MAX_INTERCEPTOR_RETRIES as usize + 1
+    );
+}
+
+#[tokio::test]
+async fn connection_reset_retried() {
+    let server = MockServer::start(|count, _| match count {
+        0 => Reply::Reset,
+        _ => Reply::ok(b"second try"),
+    })
+    .await;
+
+    let response = HttpClient::new(Easy2::new(InMemoryHandler::new()))
+        .exchange(HttpRequestBuilder::new(server.url("/")).build())
+        .await
+        .unwrap();
+    assert_eq!(response.body, b"second try");
+    assert_eq!(response.stats.connection_resets, 1);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn request_timeout_retried() {
+    let server = MockServer::start(|count, _| match count {
+        0 => Reply::status(408, &[], b"too slow"),
+        _ => Reply::ok(b"on time"),
+    })
+    .await;
+
+    let response = HttpClient::new(Easy2::new(InMemoryHandler::new()))
+        .exchange(HttpRequestBuilder::new(server.url("/")).build())
+        .await
+        .unwrap();
+    assert_eq!(response.status_code, StatusCode::OK);
+    assert_eq!(response.body, b"on time");
+    assert_eq!(response.stats.server_timeouts, 1);
+}
+
+#[tokio::test]
+async fn empty_ok_retried_when_enabled() {
+    let server = MockServer::start(|count, _| match count {
+        0 => Reply::ok(b""),
+        _ => Reply::ok(b"filled"),
+    })
+    .await;
+
+    let response = HttpClient::new(Easy2::new(InMemoryHandler::new()))
+        .retry_on_empty_response(true)
+        .exchange(HttpRequestBuilder::new(server.url("/")).build())
+        .await
+        .unwrap();
+    assert_eq!(response.body, b"filled");
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn empty_ok_kept_by_default() {
+    let server = MockServer::start(|_, _| Reply::ok(b"")).await;
+
+    let response = HttpClient::new(Easy2::new(InMemoryHandler::new()))
+        .exchange(HttpRequestBuilder::new(server.url("/")).build())
+        .await
+        .unwrap();
+    assert!(response.body.is_empty());
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn max_bytes_of_chunked_body() {
+    // Chunked, so that only the handler can tell the body is too large.
+    let server = MockServer::start(|_, _| {
+        Reply::Raw(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+              8\r\n01234567\r\n8\r\n89abcdef\r\n0\r\n\r\n"
+                .to_vec(),
+        )
+    })
+    .await;
+
+    let result = HttpClient::new(Easy2::new(InMemoryHandler::new().max_bytes(10)))
+        .exchange(HttpRequestBuilder::new(server.url("/")).build())
+        .await;
+    match result {
+        Err(Error::ResponseTooLarge { limit, actual }) => {
+            assert_eq!((limit, actual), (10, 16));
+        }
+        other => panic!("expected ResponseTooLarge, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn download_reset_before_retry() {
+    let server = MockServer::start(|count, _| match count {
+        0 => Reply::status(408, &[], b"discard me"),
+        _ => Reply::ok(b"file contents"),
+    })
+    .await;
+    let path = std::env::temp_dir().join(format!("download-reset-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let response = HttpClient::new(Easy2::new(DownloadHandler::new(path.clone()).unwrap()))
+        .exchange(HttpRequestBuilder::new(server.url("/file")).build())
+        .await
+        .unwrap();
+    let contents = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(response.status_code, StatusCode::OK);
+    assert_eq!(contents, b"file contents");
+}